@@ -0,0 +1,266 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::types::{defined_field_types, IdlDefinedFields, IdlRepr, IdlReprModifier, IdlSerialization, IdlType, IdlTypeDef, IdlTypeDefTy};
+use crate::{anchor_sighash, field_ident, type_ident, Idl, IdlAccount};
+
+// zero-copy views over account data, one per entry in the IDL `accounts` section
+pub(crate) fn generate_accounts_module(idl: &Idl, anchor: bool) -> syn::Result<TokenStream> {
+    let views = idl.accounts
+        .iter()
+        .map(|account| generate_account_view(account, idl, anchor))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        pub mod accounts {
+            #[allow(unused_imports)]
+            use super::{codec, types, ID};
+            #[allow(unused_imports)]
+            use pinocchio::{
+                account_info::{AccountInfo, Ref},
+                program_error::ProgramError,
+                pubkey::Pubkey,
+            };
+
+            // view constructors check the data covers every getter
+            #[allow(dead_code)]
+            #[inline(always)]
+            fn bytes_at<const N: usize>(data: &[u8], offset: usize) -> &[u8; N] {
+                data[offset..offset + N].try_into().unwrap()
+            }
+
+            #(#views)*
+        }
+    })
+}
+
+// v2 IDLs carry the discriminator, legacy anchor hashes the account name, native programs have none
+fn account_discriminator(account: &IdlAccount, anchor: bool) -> Vec<u8> {
+    match (&account.discriminator, anchor) {
+        (Some(discriminator), _) => discriminator.clone(),
+        (None, true) => anchor_sighash("account", &account.name).to_vec(),
+        (None, false) => Vec::new(),
+    }
+}
+
+fn generate_account_view(account: &IdlAccount, idl: &Idl, anchor: bool) -> syn::Result<TokenStream> {
+    let type_def = idl.types
+        .iter()
+        .find(|type_def| type_def.name == account.name)
+        .ok_or_else(|| syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!("Account '{}' in IDL '{}' has no type definition", account.name, idl.name)
+        ))?;
+
+    let ident = type_ident(&account.name)?;
+    let discriminator = account_discriminator(account, anchor);
+    let discriminator_len = discriminator.len();
+
+    let fields: Vec<(String, &IdlType)> = match &type_def.ty {
+        IdlTypeDefTy::Struct { fields: Some(IdlDefinedFields::Named(named)) } => {
+            named.iter().map(|field| (field.name.clone(), &field.ty)).collect()
+        }
+        IdlTypeDefTy::Struct { fields } => defined_field_types(fields)
+            .into_iter()
+            .enumerate()
+            .map(|(i, ty)| (format!("field_{}", i), ty))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let (len, len_doc, getters, decode) = match &type_def.serialization {
+        // borsh has no padding, so offsets are known up to the first dynamically sized field
+        IdlSerialization::Borsh => {
+            let mut offset = discriminator_len;
+            let mut getters = Vec::new();
+            for (name, ty) in fields {
+                let Some(len) = ty.fixed_encoded_len(idl) else {
+                    break;
+                };
+                getters.push(generate_field_getter(&name, ty, offset)?);
+                offset += len;
+            }
+            let decode = quote! {
+                /// Decodes every field into the owned type, including ones without a getter.
+                pub fn decode(&self) -> Result<types::#ident, ProgramError> {
+                    codec::BorshDeserialize::deserialize(&mut &self.data[#discriminator_len..])
+                }
+            };
+            (offset, "Bytes covered by the field getters, discriminator included.", getters, decode)
+        }
+        // zero-copy accounts are the struct's memory, padding included, and have no borsh encoding to decode
+        IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe => {
+            let (offsets, size, _) = c_struct_layout(type_def, idl).ok_or_else(|| syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("Account '{}' in IDL '{}' is zero-copy without a C layout, only `repr(C)` and \
+                         `repr(transparent)` structs of fixed size fields are supported",
+                       account.name,
+                       idl.name)
+            ))?;
+            let mut getters = Vec::new();
+            for ((name, ty), offset) in fields.into_iter().zip(offsets) {
+                // nested structs and other arrays have no getter, `as_bytes` covers them
+                if zero_copy_read(ty, 0).is_some() {
+                    getters.push(generate_field_getter(&name, ty, discriminator_len + offset)?);
+                }
+            }
+            (discriminator_len + size, "Size of the `repr(C)` struct, discriminator included.", getters, quote! {})
+        }
+        IdlSerialization::Custom(format) => {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("Account '{}' in IDL '{}' uses the custom serialization '{}', which has no known layout",
+                       account.name,
+                       idl.name,
+                       format)
+            ));
+        }
+    };
+
+    let doc = format!("Zero-copy view of a `{}` account owned by this program.", account.name);
+
+    Ok(quote! {
+        #[doc = #doc]
+        pub struct #ident<'a> {
+            data: Ref<'a, [u8]>,
+        }
+
+        impl<'a> #ident<'a> {
+            pub const DISCRIMINATOR: [u8; #discriminator_len] = [#(#discriminator),*];
+
+            #[doc = #len_doc]
+            pub const LEN: usize = #len;
+
+            /// Checks the owner and discriminator, then borrows the account data.
+            pub fn from_account_info(info: &'a AccountInfo) -> Result<Self, ProgramError> {
+                if !info.is_owned_by(&ID) {
+                    return Err(ProgramError::InvalidAccountOwner);
+                }
+                let data = info.try_borrow_data()?;
+                if data.len() < Self::LEN || !data.starts_with(&Self::DISCRIMINATOR) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                Ok(Self { data })
+            }
+
+            /// Raw account data, discriminator included.
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.data
+            }
+
+            #decode
+
+            #(#getters)*
+        }
+    })
+}
+
+fn generate_field_getter(name: &str, ty: &IdlType, offset: usize) -> syn::Result<TokenStream> {
+    let getter = field_ident(name)?;
+
+    Ok(match zero_copy_read(ty, offset) {
+        Some((return_type, read)) => quote! {
+            #[inline(always)]
+            pub fn #getter(&self) -> #return_type {
+                #read
+            }
+        },
+        // composite fields are decoded on access
+        None => {
+            let rust_type = ty.rust_type()?;
+            quote! {
+                pub fn #getter(&self) -> Result<#rust_type, ProgramError> {
+                    codec::BorshDeserialize::deserialize(&mut &self.data[#offset..])
+                }
+            }
+        }
+    })
+}
+
+// (field offsets, size, alignment) of a `repr(C)` or `repr(transparent)` struct, `None` for any other layout
+fn c_struct_layout(type_def: &IdlTypeDef, idl: &Idl) -> Option<(Vec<usize>, usize, usize)> {
+    let transparent = IdlReprModifier::default();
+    let modifier = match &type_def.repr {
+        Some(IdlRepr::C(modifier)) => modifier,
+        Some(IdlRepr::Transparent) => &transparent,
+        Some(IdlRepr::Rust(_)) | None => return None,
+    };
+    let IdlTypeDefTy::Struct { fields } = &type_def.ty else {
+        return None;
+    };
+
+    let mut offsets = Vec::new();
+    let mut offset: usize = 0;
+    let mut struct_align = modifier.align.unwrap_or(1);
+    for ty in defined_field_types(fields) {
+        let (size, align) = c_layout(ty, idl)?;
+        let align = if modifier.packed { 1 } else { align };
+        offset = offset.next_multiple_of(align);
+        offsets.push(offset);
+        offset = offset.checked_add(size)?;
+        struct_align = struct_align.max(align);
+    }
+    Some((offsets, offset.next_multiple_of(struct_align), struct_align))
+}
+
+// (size, alignment) of a field, as laid out on the SBF target, which aligns 128-bit integers to 8 bytes
+fn c_layout(ty: &IdlType, idl: &Idl) -> Option<(usize, usize)> {
+    match ty {
+        IdlType::Bool | IdlType::U8 | IdlType::I8 => Some((1, 1)),
+        IdlType::U16 | IdlType::I16 => Some((2, 2)),
+        IdlType::U32 | IdlType::I32 | IdlType::F32 => Some((4, 4)),
+        IdlType::U64 | IdlType::I64 | IdlType::F64 => Some((8, 8)),
+        IdlType::U128 | IdlType::I128 => Some((16, 8)),
+        IdlType::Pubkey => Some((32, 1)),
+        IdlType::Array(inner, len) => {
+            let (size, align) = c_layout(inner, idl)?;
+            Some((size.checked_mul(*len)?, align))
+        }
+        IdlType::Defined(name) => {
+            let type_def = idl.types.iter().find(|type_def| type_def.name == *name)?;
+            let (_, size, align) = c_struct_layout(type_def, idl)?;
+            Some((size, align))
+        }
+        _ => None,
+    }
+}
+
+// types read straight out of the account data: (return type, expression)
+fn zero_copy_read(ty: &IdlType, offset: usize) -> Option<(TokenStream, TokenStream)> {
+    let number = |number: TokenStream| {
+        let read = quote! { #number::from_le_bytes(*bytes_at(&self.data, #offset)) };
+        Some((number, read))
+    };
+
+    match ty {
+        IdlType::U8 => number(quote! { u8 }),
+        IdlType::U16 => number(quote! { u16 }),
+        IdlType::U32 => number(quote! { u32 }),
+        IdlType::U64 => number(quote! { u64 }),
+        IdlType::U128 => number(quote! { u128 }),
+        IdlType::I8 => number(quote! { i8 }),
+        IdlType::I16 => number(quote! { i16 }),
+        IdlType::I32 => number(quote! { i32 }),
+        IdlType::I64 => number(quote! { i64 }),
+        IdlType::I128 => number(quote! { i128 }),
+        IdlType::F32 => number(quote! { f32 }),
+        IdlType::F64 => number(quote! { f64 }),
+        IdlType::Bool => Some((quote! { bool }, quote! { self.data[#offset] != 0 })),
+        IdlType::Pubkey => Some((quote! { &Pubkey }, quote! { bytes_at(&self.data, #offset) })),
+        IdlType::Array(inner, len) if **inner == IdlType::U8 => {
+            Some((quote! { &[u8; #len] }, quote! { bytes_at(&self.data, #offset) }))
+        }
+        // any tag other than 1 reads as empty
+        IdlType::COption(inner) => {
+            let (inner_type, inner_read) = zero_copy_read(inner, offset + 4)?;
+            let read = quote! {
+                match u32::from_le_bytes(*bytes_at(&self.data, #offset)) {
+                    1 => Some(#inner_read),
+                    _ => None,
+                }
+            };
+            Some((quote! { Option<#inner_type> }, read))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,69 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+
+use crate::{to_snake_case, Account, Idl, Instruction};
+
+// accounts every caller must pass at a known key, e.g. `system_program` or pump's `event_authority`
+pub(crate) fn generate_addresses_module(idl: &Idl) -> syn::Result<TokenStream> {
+    let mut constants: Vec<(Ident, &str)> = Vec::new();
+    for instruction in &idl.instructions {
+        for account in &instruction.accounts {
+            let (Some(address), Some(name)) = (&account.address, address_constant(instruction, account, idl)) else {
+                continue;
+            };
+            if !constants.iter().any(|(existing, _)| *existing == name) {
+                constants.push((name, address));
+            }
+        }
+    }
+
+    let constants = constants
+        .iter()
+        .map(|(name, address)| {
+            let bytes = five8_const::try_decode_32_const(address).map_err(|e| {
+                syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("Invalid address '{}' for account '{}' in IDL '{}' ({:?})", address, name, idl.name, e)
+                )
+            })?;
+            let doc = format!("`{}`", address);
+            Ok(quote! {
+                #[doc = #doc]
+                pub const #name: Pubkey = [#(#bytes),*];
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        pub mod addresses {
+            use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+            #(#constants)*
+
+            /// Looks up the account at `address`, e.g. in the entrypoint's account list.
+            pub fn find_account<'a>(accounts: &'a [AccountInfo], address: &Pubkey) -> Result<&'a AccountInfo, ProgramError> {
+                accounts
+                    .iter()
+                    .find(|account| account.key() == address)
+                    .ok_or(ProgramError::NotEnoughAccountKeys)
+            }
+        }
+    })
+}
+
+// named after the account, prefixed with the instruction when instructions disagree on the address
+pub(crate) fn address_constant(instruction: &Instruction, account: &Account, idl: &Idl) -> Option<Ident> {
+    let address = account.address.as_ref()?;
+    let name = to_snake_case(&account.name);
+
+    let conflicting = idl.instructions
+        .iter()
+        .flat_map(|other| &other.accounts)
+        .any(|other| to_snake_case(&other.name) == name && other.address.as_ref().is_some_and(|other| other != address));
+
+    if conflicting {
+        Some(format_ident!("{}_{}", to_snake_case(&instruction.name).to_uppercase(), name.to_uppercase()))
+    } else {
+        Some(format_ident!("{}", name.to_uppercase()))
+    }
+}
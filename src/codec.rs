@@ -0,0 +1,412 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+// generated into every program module, so users don't need borsh (or std) as a dependency
+pub(crate) fn generate_codec_module() -> TokenStream {
+    quote! {
+        pub mod codec {
+            use super::alloc::{string::String, vec::Vec};
+            use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+
+            /// Borsh encoding into a caller provided buffer.
+            pub trait BorshSerialize {
+                /// Length of every encoding when it doesn't depend on the value, which `COption`
+                /// zero fills when empty.
+                const FIXED_LEN: Option<usize> = None;
+
+                /// Number of bytes `serialize` writes.
+                fn serialized_len(&self) -> usize;
+
+                /// Writes the encoding to the front of `buf`, returning the number of bytes written.
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError>;
+            }
+
+            /// Borsh decoding from the front of a byte slice.
+            pub trait BorshDeserialize: Sized {
+                /// Reads a value, advancing `data` past the bytes consumed.
+                fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError>;
+            }
+
+            #[inline(always)]
+            pub fn write_bytes(bytes: &[u8], buf: &mut [u8]) -> Result<usize, ProgramError> {
+                buf.get_mut(..bytes.len())
+                    .ok_or(ProgramError::BorshIoError)?
+                    .copy_from_slice(bytes);
+                Ok(bytes.len())
+            }
+
+            // `FIXED_LEN` of a tuple, fixed only when every element is
+            pub const fn fixed_len_sum(lens: &[Option<usize>]) -> Option<usize> {
+                let mut sum = 0;
+                let mut i = 0;
+                while i < lens.len() {
+                    match lens[i] {
+                        Some(len) => sum += len,
+                        None => return None,
+                    }
+                    i += 1;
+                }
+                Some(sum)
+            }
+
+            #[inline(always)]
+            pub fn read_bytes<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], ProgramError> {
+                if data.len() < len {
+                    return Err(ProgramError::BorshIoError);
+                }
+                let (bytes, rest) = data.split_at(len);
+                *data = rest;
+                Ok(bytes)
+            }
+
+            /// Instruction data serialized on the stack, `N` is the largest encoding the IDL allows.
+            #[derive(Clone, Copy)]
+            pub struct InstructionData<const N: usize> {
+                buf: [u8; N],
+                len: usize,
+            }
+
+            impl<const N: usize> InstructionData<N> {
+                #[inline(always)]
+                pub fn new(buf: [u8; N], len: usize) -> Self {
+                    Self { buf, len: len.min(N) }
+                }
+
+                #[inline(always)]
+                pub fn as_slice(&self) -> &[u8] {
+                    &self.buf[..self.len]
+                }
+            }
+
+            impl<const N: usize> core::ops::Deref for InstructionData<N> {
+                type Target = [u8];
+
+                #[inline(always)]
+                fn deref(&self) -> &[u8] {
+                    self.as_slice()
+                }
+            }
+
+            impl<const N: usize> core::fmt::Debug for InstructionData<N> {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    self.as_slice().fmt(f)
+                }
+            }
+
+            /// Return data left by the CPI that just finished, checked to come from `program_id`.
+            pub fn return_data(program_id: &Pubkey) -> Result<pinocchio::cpi::ReturnData, ProgramError> {
+                // nothing set reads like a truncated encoding
+                let data = pinocchio::cpi::get_return_data().ok_or(ProgramError::BorshIoError)?;
+                if data.program_id() != program_id {
+                    return Err(ProgramError::IncorrectProgramId);
+                }
+                Ok(data)
+            }
+
+            /// [`return_data`] decoded as `T`.
+            pub fn read_return_data<T: BorshDeserialize>(program_id: &Pubkey) -> Result<T, ProgramError> {
+                T::deserialize(&mut return_data(program_id)?.as_slice())
+            }
+
+            macro_rules! impl_number {
+                ($($number:ty),*) => {$(
+                    impl BorshSerialize for $number {
+                        const FIXED_LEN: Option<usize> = Some(core::mem::size_of::<$number>());
+
+                        #[inline(always)]
+                        fn serialized_len(&self) -> usize {
+                            core::mem::size_of::<$number>()
+                        }
+
+                        #[inline(always)]
+                        fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                            write_bytes(&self.to_le_bytes(), buf)
+                        }
+                    }
+
+                    impl BorshDeserialize for $number {
+                        #[inline(always)]
+                        fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                            let mut bytes = [0u8; core::mem::size_of::<$number>()];
+                            bytes.copy_from_slice(read_bytes(data, core::mem::size_of::<$number>())?);
+                            Ok(<$number>::from_le_bytes(bytes))
+                        }
+                    }
+                )*};
+            }
+
+            impl_number!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+            impl BorshSerialize for bool {
+                const FIXED_LEN: Option<usize> = Some(1);
+
+                #[inline(always)]
+                fn serialized_len(&self) -> usize {
+                    1
+                }
+
+                #[inline(always)]
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    write_bytes(&[*self as u8], buf)
+                }
+            }
+
+            impl BorshDeserialize for bool {
+                #[inline(always)]
+                fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                    match read_bytes(data, 1)?[0] {
+                        0 => Ok(false),
+                        1 => Ok(true),
+                        _ => Err(ProgramError::BorshIoError),
+                    }
+                }
+            }
+
+            impl<T: BorshSerialize, const N: usize> BorshSerialize for [T; N] {
+                const FIXED_LEN: Option<usize> = match T::FIXED_LEN {
+                    Some(len) => Some(len * N),
+                    None => None,
+                };
+
+                fn serialized_len(&self) -> usize {
+                    self.iter().map(BorshSerialize::serialized_len).sum()
+                }
+
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    let mut offset = 0;
+                    for item in self {
+                        offset += item.serialize(&mut buf[offset..])?;
+                    }
+                    Ok(offset)
+                }
+            }
+
+            impl<T: BorshDeserialize, const N: usize> BorshDeserialize for [T; N] {
+                fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                    let items: [Option<T>; N] = core::array::from_fn(|_| T::deserialize(data).ok());
+                    if items.iter().any(Option::is_none) {
+                        return Err(ProgramError::BorshIoError);
+                    }
+                    Ok(items.map(|item| item.unwrap()))
+                }
+            }
+
+            // u32 length prefix, then the items
+            impl<T: BorshSerialize> BorshSerialize for [T] {
+                fn serialized_len(&self) -> usize {
+                    4 + self.iter().map(BorshSerialize::serialized_len).sum::<usize>()
+                }
+
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    let mut offset = (self.len() as u32).serialize(buf)?;
+                    for item in self {
+                        offset += item.serialize(&mut buf[offset..])?;
+                    }
+                    Ok(offset)
+                }
+            }
+
+            impl<T: BorshSerialize> BorshSerialize for Vec<T> {
+                #[inline(always)]
+                fn serialized_len(&self) -> usize {
+                    self.as_slice().serialized_len()
+                }
+
+                #[inline(always)]
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    self.as_slice().serialize(buf)
+                }
+            }
+
+            impl<T: BorshDeserialize> BorshDeserialize for Vec<T> {
+                fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                    let len = u32::deserialize(data)? as usize;
+                    // never trust the prefix for the allocation size
+                    let mut items = Vec::with_capacity(len.min(data.len()));
+                    for _ in 0..len {
+                        items.push(T::deserialize(data)?);
+                    }
+                    Ok(items)
+                }
+            }
+
+            impl BorshSerialize for str {
+                #[inline(always)]
+                fn serialized_len(&self) -> usize {
+                    4 + self.len()
+                }
+
+                #[inline(always)]
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    let offset = (self.len() as u32).serialize(buf)?;
+                    Ok(offset + write_bytes(self.as_bytes(), &mut buf[offset..])?)
+                }
+            }
+
+            impl BorshSerialize for String {
+                #[inline(always)]
+                fn serialized_len(&self) -> usize {
+                    self.as_str().serialized_len()
+                }
+
+                #[inline(always)]
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    self.as_str().serialize(buf)
+                }
+            }
+
+            impl BorshDeserialize for String {
+                fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                    let len = u32::deserialize(data)? as usize;
+                    let bytes = read_bytes(data, len)?;
+                    core::str::from_utf8(bytes)
+                        .map(String::from)
+                        .map_err(|_| ProgramError::BorshIoError)
+                }
+            }
+
+            // u8 tag, value only when present
+            impl<T: BorshSerialize> BorshSerialize for Option<T> {
+                fn serialized_len(&self) -> usize {
+                    1 + self.as_ref().map_or(0, BorshSerialize::serialized_len)
+                }
+
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    match self {
+                        Some(value) => {
+                            let offset = 1u8.serialize(buf)?;
+                            Ok(offset + value.serialize(&mut buf[offset..])?)
+                        }
+                        None => 0u8.serialize(buf),
+                    }
+                }
+            }
+
+            impl<T: BorshDeserialize> BorshDeserialize for Option<T> {
+                fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                    match u8::deserialize(data)? {
+                        0 => Ok(None),
+                        1 => Ok(Some(T::deserialize(data)?)),
+                        _ => Err(ProgramError::BorshIoError),
+                    }
+                }
+            }
+
+            /// Solana's C-compatible option used in spl account state: a u32 tag followed by
+            /// the value, which is zeroed but still present when the option is empty. The zeroes
+            /// span `T::FIXED_LEN`, so `T` needs a fixed size encoding.
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+            pub struct COption<T>(pub Option<T>);
+
+            impl<T> From<Option<T>> for COption<T> {
+                fn from(option: Option<T>) -> Self {
+                    COption(option)
+                }
+            }
+
+            impl<T> From<COption<T>> for Option<T> {
+                fn from(option: COption<T>) -> Self {
+                    option.0
+                }
+            }
+
+            impl<T: BorshSerialize> BorshSerialize for COption<T> {
+                const FIXED_LEN: Option<usize> = match T::FIXED_LEN {
+                    Some(len) => Some(4 + len),
+                    None => None,
+                };
+
+                fn serialized_len(&self) -> usize {
+                    match &self.0 {
+                        Some(value) => 4 + value.serialized_len(),
+                        None => 4 + T::FIXED_LEN.unwrap_or(0),
+                    }
+                }
+
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    match &self.0 {
+                        Some(value) => {
+                            let offset = 1u32.serialize(buf)?;
+                            Ok(offset + value.serialize(&mut buf[offset..])?)
+                        }
+                        None => {
+                            let offset = 0u32.serialize(buf)?;
+                            let padding = buf
+                                .get_mut(offset..offset + T::FIXED_LEN.unwrap_or(0))
+                                .ok_or(ProgramError::BorshIoError)?;
+                            padding.fill(0);
+                            Ok(offset + padding.len())
+                        }
+                    }
+                }
+            }
+
+            impl<T: BorshDeserialize> BorshDeserialize for COption<T> {
+                fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                    let tag = u32::deserialize(data)?;
+                    let value = T::deserialize(data)?;
+                    match tag {
+                        0 => Ok(COption(None)),
+                        1 => Ok(COption(Some(value))),
+                        _ => Err(ProgramError::BorshIoError),
+                    }
+                }
+            }
+
+            macro_rules! impl_tuple {
+                ($($name:ident),+) => {
+                    impl<$($name: BorshSerialize),+> BorshSerialize for ($($name,)+) {
+                        const FIXED_LEN: Option<usize> = fixed_len_sum(&[$(<$name as BorshSerialize>::FIXED_LEN),+]);
+
+                        #[allow(non_snake_case)]
+                        fn serialized_len(&self) -> usize {
+                            let ($($name,)+) = self;
+                            0 $(+ $name.serialized_len())+
+                        }
+
+                        #[allow(non_snake_case)]
+                        fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                            let ($($name,)+) = self;
+                            let mut offset = 0;
+                            $(offset += $name.serialize(&mut buf[offset..])?;)+
+                            Ok(offset)
+                        }
+                    }
+
+                    impl<$($name: BorshDeserialize),+> BorshDeserialize for ($($name,)+) {
+                        fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                            Ok(($($name::deserialize(data)?,)+))
+                        }
+                    }
+                };
+            }
+
+            impl_tuple!(A);
+            impl_tuple!(A, B);
+            impl_tuple!(A, B, C);
+            impl_tuple!(A, B, C, D);
+            impl_tuple!(A, B, C, D, E);
+            impl_tuple!(A, B, C, D, E, F);
+            impl_tuple!(A, B, C, D, E, F, G);
+            impl_tuple!(A, B, C, D, E, F, G, H);
+            impl_tuple!(A, B, C, D, E, F, G, H, I);
+            impl_tuple!(A, B, C, D, E, F, G, H, I, J);
+            impl_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+            impl_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+            impl<T: BorshSerialize + ?Sized> BorshSerialize for &T {
+                const FIXED_LEN: Option<usize> = T::FIXED_LEN;
+
+                #[inline(always)]
+                fn serialized_len(&self) -> usize {
+                    (**self).serialized_len()
+                }
+
+                #[inline(always)]
+                fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                    (**self).serialize(buf)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde::{Deserialize, Serialize};
+
+use crate::{type_ident, Idl};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct IdlErrorCode {
+    pub(crate) code: u32,
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) msg: Option<String>,
+}
+
+// custom error codes the program returns, so failed CPIs can be matched by name
+pub(crate) fn generate_errors_module(idl: &Idl) -> syn::Result<TokenStream> {
+    let variants = idl.errors.iter().map(|error| type_ident(&error.name)).collect::<syn::Result<Vec<_>>>()?;
+    let codes: Vec<_> = idl.errors.iter().map(|error| error.code).collect();
+    let messages: Vec<_> = idl.errors
+        .iter()
+        .map(|error| error.msg.clone().unwrap_or_else(|| error.name.clone()))
+        .collect();
+    let docs = messages.iter().zip(&codes).map(|(msg, code)| format!("{} ({})", msg, code));
+
+    Ok(quote! {
+        pub mod errors {
+            use pinocchio::program_error::ProgramError;
+
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub enum Error {
+                #(#[doc = #docs] #variants),*
+            }
+
+            impl Error {
+                /// The code returned in `ProgramError::Custom`.
+                pub const fn code(self) -> u32 {
+                    match self {
+                        #(Error::#variants => #codes),*
+                    }
+                }
+
+                pub const fn msg(self) -> &'static str {
+                    match self {
+                        #(Error::#variants => #messages),*
+                    }
+                }
+
+                /// Typed error behind a failed CPI, `None` for builtin errors and unknown codes.
+                pub fn from_program_error(error: &ProgramError) -> Option<Self> {
+                    match error {
+                        ProgramError::Custom(code) => Self::try_from(*code).ok(),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl TryFrom<u32> for Error {
+                type Error = u32;
+
+                fn try_from(code: u32) -> Result<Self, u32> {
+                    match code {
+                        #(#codes => Ok(Error::#variants),)*
+                        _ => Err(code),
+                    }
+                }
+            }
+
+            impl From<Error> for ProgramError {
+                fn from(error: Error) -> Self {
+                    ProgramError::Custom(error.code())
+                }
+            }
+
+            impl core::fmt::Display for Error {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    f.write_str(self.msg())
+                }
+            }
+        }
+    })
+}
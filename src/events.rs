@@ -0,0 +1,115 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde::{Deserialize, Serialize};
+
+use crate::types::IdlField;
+use crate::{anchor_sighash, type_ident, Idl};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct IdlEvent {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) discriminator: Option<Vec<u8>>,
+    // legacy IDLs list the fields inline, v2 points at the `types` entry of the same name
+    #[serde(default)]
+    pub(crate) fields: Option<Vec<IdlField>>,
+}
+
+// v2 IDLs carry the discriminator, legacy anchor hashes the event name, other programs have none
+fn event_discriminator(event: &IdlEvent, anchor: bool) -> Vec<u8> {
+    match (&event.discriminator, anchor) {
+        (Some(discriminator), _) => discriminator.clone(),
+        (None, true) => anchor_sighash("event", &event.name).to_vec(),
+        (None, false) => Vec::new(),
+    }
+}
+
+pub(crate) fn generate_events_module(idl: &Idl, anchor: bool) -> syn::Result<TokenStream> {
+    for event in &idl.events {
+        if !idl.types.iter().any(|type_def| type_def.name == event.name) {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("Event '{}' in IDL '{}' has no type definition", event.name, idl.name)
+            ));
+        }
+    }
+
+    let idents = idl.events.iter().map(|event| type_ident(&event.name)).collect::<syn::Result<Vec<_>>>()?;
+    let discriminators: Vec<_> = idl.events.iter().map(|event| event_discriminator(event, anchor)).collect();
+    let discriminator_lens = discriminators.iter().map(Vec::len);
+    let discriminator_bytes = discriminators.iter().map(|discriminator| quote! { [#(#discriminator),*] });
+
+    Ok(quote! {
+        pub mod events {
+            #[allow(unused_imports)]
+            use super::{alloc, codec, types};
+            use pinocchio::program_error::ProgramError;
+
+            #(pub use super::types::#idents;)*
+
+            #(
+                impl #idents {
+                    pub const DISCRIMINATOR: [u8; #discriminator_lens] = #discriminator_bytes;
+                }
+            )*
+
+            /// Prefix of the self-CPI instruction data written by anchor's `emit_cpi!`.
+            pub const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+
+            /// Prefix of the log line written by anchor's `emit!`.
+            pub const LOG_PREFIX: &str = "Program data: ";
+
+            #[derive(Clone, Debug, PartialEq)]
+            pub enum Event {
+                #(#idents(#idents)),*
+            }
+
+            /// Decodes an event from any of the forms anchor emits: the raw discriminator and
+            /// payload, a `Program data: <base64>` log line, or `emit_cpi!` instruction data.
+            pub fn decode_event(data: &[u8]) -> Result<Event, ProgramError> {
+                if let Some(encoded) = data.strip_prefix(LOG_PREFIX.as_bytes()) {
+                    let decoded = decode_base64(encoded).ok_or(ProgramError::InvalidInstructionData)?;
+                    return decode_event(&decoded);
+                }
+                #[allow(unused_variables)]
+                let data = data.strip_prefix(&EVENT_IX_TAG).unwrap_or(data);
+
+                #(
+                    if let Some(mut payload) = data.strip_prefix(&#idents::DISCRIMINATOR) {
+                        return Ok(Event::#idents(codec::BorshDeserialize::deserialize(&mut payload)?));
+                    }
+                )*
+                Err(ProgramError::InvalidInstructionData)
+            }
+
+            // standard alphabet, padding optional
+            fn decode_base64(encoded: &[u8]) -> Option<alloc::vec::Vec<u8>> {
+                let encoded = encoded.trim_ascii_end();
+                let encoded = encoded.strip_suffix(b"==")
+                    .or_else(|| encoded.strip_suffix(b"="))
+                    .unwrap_or(encoded);
+
+                let mut decoded = alloc::vec::Vec::with_capacity(encoded.len() * 3 / 4);
+                let mut buffer = 0u32;
+                let mut bits = 0;
+                for &c in encoded {
+                    let value = match c {
+                        b'A'..=b'Z' => c - b'A',
+                        b'a'..=b'z' => c - b'a' + 26,
+                        b'0'..=b'9' => c - b'0' + 52,
+                        b'+' => 62,
+                        b'/' => 63,
+                        _ => return None,
+                    };
+                    buffer = (buffer << 6) | value as u32;
+                    bits += 6;
+                    if bits >= 8 {
+                        bits -= 8;
+                        decoded.push((buffer >> bits) as u8);
+                    }
+                }
+                Some(decoded)
+            }
+        }
+    })
+}
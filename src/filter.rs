@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+
+use syn::LitStr;
+
+use crate::types::{defined_field_types, IdlType, IdlTypeDefTy};
+use crate::{to_snake_case, Idl};
+
+// `only` and `skip` pick instructions, then the types nothing kept refers to are dropped
+pub(crate) fn filter_instructions(idl: &mut Idl, only: Option<&[LitStr]>, skip: &[LitStr]) -> syn::Result<()> {
+    if only.is_none() && skip.is_empty() {
+        return Ok(());
+    }
+
+    // "mintTo" and "mint_to" both name the same instruction
+    let matches = |instruction: &str, name: &LitStr| to_snake_case(instruction) == to_snake_case(&name.value());
+    for name in only.unwrap_or_default().iter().chain(skip) {
+        if !idl.instructions.iter().any(|instruction| matches(&instruction.name, name)) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("Instruction '{}' not found in IDL '{}'", name.value(), idl.name)
+            ));
+        }
+    }
+    idl.instructions.retain(|instruction| {
+        only.is_none_or(|only| only.iter().any(|name| matches(&instruction.name, name)))
+            && !skip.iter().any(|name| matches(&instruction.name, name))
+    });
+
+    // account layouts and events aren't tied to instructions by name, so they stay along with their types
+    let mut pending: Vec<String> = idl.instructions
+        .iter()
+        .flat_map(|instruction| instruction.args.iter().map(|arg| &arg.arg_type).chain(&instruction.returns))
+        .flat_map(IdlType::defined_names)
+        .map(str::to_string)
+        .chain(idl.accounts.iter().map(|account| account.name.clone()))
+        .chain(idl.events.iter().map(|event| event.name.clone()))
+        .collect();
+    let mut reachable = HashSet::new();
+    while let Some(name) = pending.pop() {
+        let Some(type_def) = idl.types.iter().find(|type_def| type_def.name == name) else {
+            continue;
+        };
+        if reachable.insert(name) {
+            pending.extend(type_def_references(&type_def.ty).into_iter().map(str::to_string));
+        }
+    }
+    idl.types.retain(|type_def| reachable.contains(&type_def.name));
+
+    // errors stay as well: the IDL doesn't say which instruction fails with which error
+    Ok(())
+}
+
+fn type_def_references(ty: &IdlTypeDefTy) -> Vec<&str> {
+    match ty {
+        IdlTypeDefTy::Struct { fields } => defined_field_types(fields).into_iter().flat_map(IdlType::defined_names).collect(),
+        IdlTypeDefTy::Enum { variants } => variants
+            .iter()
+            .flat_map(|variant| defined_field_types(&variant.fields))
+            .flat_map(IdlType::defined_names)
+            .collect(),
+        IdlTypeDefTy::Alias { alias } => alias.defined_names(),
+    }
+}
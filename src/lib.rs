@@ -5,182 +5,1085 @@ use quote::quote;
 use syn::{parse_macro_input, LitStr, Token, Ident};
 use serde::{Deserialize, Serialize};
 
+mod accounts;
+mod addresses;
+mod codec;
+mod errors;
+mod events;
+mod filter;
+mod merge;
+mod pda;
+mod types;
+
+use errors::IdlErrorCode;
+use events::IdlEvent;
+use pda::IdlPda;
+use types::{check_type_is_encodable, IdlDefinedFields, IdlSerialization, IdlType, IdlTypeDef, IdlTypeDefTy};
+
+/// `declare_program! { name = "pump", idl_path = "..." }` generates `pub mod pump` with the program id,
+/// the IDL's types, accounts, errors and events, and a CPI helper per instruction. The id is taken from
+/// `id`, or from the address the IDL carries when `id` is left out:
+///
+/// ```
+/// sanity::declare_program! {
+///     name = "counter",
+///     idl = r#"{ "name": "counter", "instructions": [], "metadata": { "address": "Counter111111111111111111111111111111111111" } }"#
+/// }
+/// ```
+///
+/// One of the two is required:
+///
+/// ```compile_fail
+/// sanity::declare_program! {
+///     name = "counter",
+///     idl = r#"{ "name": "counter", "instructions": [] }"#
+/// }
+/// ```
+///
+/// `only = ["transfer", "mintTo"]` or `skip = [...]` generate a subset of the instructions, named as in the IDL
+/// or in snake case, and drop the types left unused. Account layouts, events and errors are all kept, the IDL
+/// doesn't say which instructions read or return them.
 #[proc_macro]
 pub fn declare_program(input: TokenStream) -> TokenStream {
     let input_struct = parse_macro_input!(input as DeclareInput);
     
-    let idl = match read_idl_file(&input_struct.idl_path, input_struct.idl_version) {
+    let idl = match load_idl(&input_struct) {
         Ok(idl) => idl,
-        Err(e) => {
-            return syn::Error::new(
-                proc_macro2::Span::call_site(),
-                format!("Failed to read IDL file '{}' as version {}: {}", 
-                       input_struct.idl_path, 
-                       input_struct.idl_version.unwrap_or(1), 
-                       e)
-            ).to_compile_error().into();
-        }
+        Err(e) => return e.to_compile_error().into(),
     };
     
-    let generated = generate_program_module(&input_struct.name, &input_struct.id, &idl);
+    match generate_program_module(&input_struct, &idl) {
+        Ok(generated) => generated.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// `#[sanity::program(idl_path = "...")] pub mod pump { ... }` takes the same keys as `declare_program!`
+/// except `name`, which is the module's. Items written in the module are kept next to the generated ones,
+/// replacing generated items and methods of the same name.
+#[proc_macro_attribute]
+pub fn program(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let module = parse_macro_input!(item as syn::ItemMod);
+    let attr = proc_macro2::TokenStream::from(attr);
     
-    generated.into()
+    match expand_program_attribute(attr, module) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
 }
 
-fn generate_program_module(module_name: &str, program_id: &Option<String>, idl: &Idl) -> proc_macro2::TokenStream {
-    let module_ident = Ident::new(module_name, proc_macro2::Span::call_site());
+fn expand_program_attribute(attr: proc_macro2::TokenStream, mut module: syn::ItemMod) -> syn::Result<proc_macro2::TokenStream> {
+    let Some((_, user_items)) = module.content.take() else {
+        return Err(syn::Error::new_spanned(&module, "Expected a module with a body, e.g. `pub mod pump {}`"));
+    };
+    if let Some(name) = attr.clone().into_iter().find(|token| matches!(token, proc_macro2::TokenTree::Ident(ident) if ident == "name")) {
+        return Err(syn::Error::new_spanned(name, "The module name is used as the program name, remove 'name'"));
+    }
+    
+    let name = module.ident.to_string();
+    let input: DeclareInput = syn::parse2(quote! { name = #name, #attr })?;
+    let idl = load_idl(&input)?;
+    
+    let generated: syn::File = syn::parse2(generate_program_items(&input, &idl)?)?;
+    let items = merge::merge_items(generated.items, user_items);
+    module.content = Some((Default::default(), items));
+    
+    Ok(quote! { #module })
+}
+
+fn load_idl(input_struct: &DeclareInput) -> syn::Result<Idl> {
+    let mut idl = read_idl(input_struct)?;
+    filter::filter_instructions(&mut idl, input_struct.only.as_deref(), &input_struct.skip)?;
+    Ok(idl)
+}
+
+fn read_idl(input_struct: &DeclareInput) -> syn::Result<Idl> {
+    match &input_struct.idl_source {
+        IdlSource::Path { literal, resolved } => read_idl_file(resolved, input_struct.idl_version).map_err(|e| {
+            syn::Error::new(
+                literal.span(),
+                format!("Failed to read IDL file '{}': {}", 
+                       resolved, 
+                       e)
+            )
+        }),
+        IdlSource::Inline(json) => parse_idl(&json.value(), input_struct.idl_version).map_err(|e| {
+            syn::Error::new(json.span(), format!("Failed to read inline IDL: {}", e))
+        }),
+    }
+}
+
+fn generate_program_module(input: &DeclareInput, idl: &Idl) -> syn::Result<proc_macro2::TokenStream> {
+    let module_ident = Ident::new(&input.name, proc_macro2::Span::call_site());
+    let items = generate_program_items(input, idl)?;
+    
+    Ok(quote! {
+        pub mod #module_ident {
+            #items
+        }
+    })
+}
+
+fn generate_program_items(input: &DeclareInput, idl: &Idl) -> syn::Result<proc_macro2::TokenStream> {
+    let module_name = &input.name;
     
     let instruction_count = idl.instructions.len();
     let instruction_names: Vec<&str> = idl.instructions.iter().map(|i| i.name.as_str()).collect();
+    let max_remaining_accounts = input.max_remaining_accounts;
+    
+    // makes cargo rebuild the crate when the IDL changes, unused so nothing ends up in the binary
+    let track_idl = match &input.idl_source {
+        IdlSource::Path { resolved: path, .. } if std::path::Path::new(path).is_absolute() => {
+            quote! { const _: &[u8] = include_bytes!(#path); }
+        }
+        _ => quote! {},
+    };
+    
+    // one call for the whole program: anchor tags instructions, accounts and events by hashing their names
+    let anchor = input.discriminator.map_or(idl.anchor, |strategy| matches!(strategy, DiscriminatorStrategy::AnchorSighash));
     
-    let program_id_code = generate_program_id_constant(program_id);
+    let program_id_code = generate_program_id_constant(input, idl)?;
+    let codec_module = codec::generate_codec_module();
+    let types_module = types::generate_types_module(idl)?;
+    let accounts_module = accounts::generate_accounts_module(idl, anchor)?;
+    let errors_module = errors::generate_errors_module(idl)?;
+    let events_module = events::generate_events_module(idl, anchor)?;
+    let pda_functions = pda::generate_pda_functions(idl)?;
+    let addresses_module = addresses::generate_addresses_module(idl)?;
     
     let cpi_functions: Vec<_> = idl.instructions
         .iter()
-        .enumerate()
-        .map(|(index, instruction)| generate_cpi_function_generic(instruction, index as u8))
-        .collect();
+        .map(|instruction| {
+            let discriminator = instruction_discriminator(instruction, input.discriminator, anchor);
+            generate_cpi_function_generic(instruction, &discriminator, idl, input)
+        })
+        .collect::<syn::Result<_>>()?;
     
-    quote! {
-        pub mod #module_ident {
-            use pinocchio::{
-                account_info::AccountInfo,
-                instruction::{AccountMeta, Instruction},
-                cpi::invoke,  
-                pubkey::Pubkey,
-                ProgramResult,
-            };
+    Ok(quote! {
+        extern crate alloc;
+        
+        #track_idl
+        
+        use pinocchio::{
+            account_info::AccountInfo,
+            instruction::{AccountMeta, Instruction, Signer},
+            cpi::invoke_signed,  
+            program_error::ProgramError,
+            pubkey::Pubkey,
+            ProgramResult,
+        };
 
-            pub const MODULE_NAME: &str = #module_name;
-            pub const INSTRUCTION_COUNT: usize = #instruction_count;
-            pub const INSTRUCTIONS: &[&str] = &[#(#instruction_names),*];
-            
+        pub const MODULE_NAME: &str = #module_name;
+        pub const INSTRUCTION_COUNT: usize = #instruction_count;
+        pub const INSTRUCTIONS: &[&str] = &[#(#instruction_names),*];
+        
+        /// Most extra accounts the `*_with_remaining` CPIs accept.
+        pub const MAX_REMAINING_ACCOUNTS: usize = #max_remaining_accounts;
+        
 
-            #program_id_code
-            
-            #(#cpi_functions)*
+        #program_id_code
+        
+        #codec_module
+        
+        #types_module
+        
+        #accounts_module
+        
+        #errors_module
+        
+        #events_module
+        
+        #addresses_module
+        
+        #(#cpi_functions)*
+        
+        #pda_functions
+    })
+}
+
+fn generate_program_id_constant(input: &DeclareInput, idl: &Idl) -> syn::Result<proc_macro2::TokenStream> {
+    let program_id = match (&input.id, &idl.address) {
+        (Some(id), Some(address)) if id.value() != *address && !input.override_address => {
+            return Err(syn::Error::new(
+                id.span(),
+                format!("Program id '{}' does not match address '{}' declared in IDL '{}'. \
+                         Remove 'id' to use the IDL address, or set 'override_address = true' to keep this id",
+                       id.value(),
+                       address,
+                       idl.name)
+            ));
+        }
+        (Some(id), _) => id.clone(),
+        // anchor 0.30+ IDLs carry the deployed address themselves
+        (None, Some(address)) => LitStr::new(address, proc_macro2::Span::call_site()),
+        // an all-zero id would only surface as a failed CPI on chain
+        (None, None) => {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("No 'id' given and IDL '{}' has no address, set 'id = \"<program id>\"'", idl.name)
+            ));
+        }
+    };
+    
+    let id_bytes = decode_program_id(&program_id, &idl.name)?;
+    
+    Ok(quote! {
+        pub const PROGRAM_ID: &str = #program_id;
+        
+        pub const ID: Pubkey = [#(#id_bytes),*];
+        
+        pub fn program_id() -> Pubkey {
+            ID
         }
+    })
+}
+
+// base58 decoding happens at expansion time so a bad id fails the build instead of the CPI
+fn decode_program_id(id: &LitStr, idl_name: &str) -> syn::Result<[u8; 32]> {
+    five8_const::try_decode_32_const(&id.value()).map_err(|e| {
+        syn::Error::new(
+            id.span(),
+            format!("Invalid program id '{}' for '{}': not a base58 encoded 32-byte pubkey ({:?})", 
+                   id.value(), 
+                   idl_name, 
+                   e)
+        )
+    })
+}
+
+fn instruction_discriminator(instruction: &Instruction, strategy: Option<DiscriminatorStrategy>, anchor: bool) -> Vec<u8> {
+    let index = instruction.index;
+    match strategy {
+        Some(DiscriminatorStrategy::AnchorSighash) => anchor_sighash("global", &to_snake_case(&instruction.name)).to_vec(),
+        Some(DiscriminatorStrategy::U8Index) => vec![index as u8],
+        Some(DiscriminatorStrategy::U32Index) => (index as u32).to_le_bytes().to_vec(),
+        Some(DiscriminatorStrategy::None) => Vec::new(),
+        // v2 IDLs carry the discriminator, legacy anchor hashes the name, native programs tag with the index
+        None => instruction.discriminator
+            .clone()
+            .unwrap_or_else(|| if anchor {
+                anchor_sighash("global", &to_snake_case(&instruction.name)).to_vec()
+            } else {
+                vec![index as u8]
+            }),
     }
 }
 
-fn generate_program_id_constant(program_id: &Option<String>) -> proc_macro2::TokenStream {
-    match program_id {
-        Some(id) => {
-            quote! {
-                pub const PROGRAM_ID: &str = #id;
-                
-                pub fn program_id() -> Pubkey {
+// sha256("<namespace>:<name>")[..8], how anchor tags instructions and accounts
+fn anchor_sighash(namespace: &str, name: &str) -> [u8; 8] {
+    use sha2::{Digest, Sha256};
+    
+    let hash = Sha256::digest(format!("{}:{}", namespace, name).as_bytes());
+    let mut sighash = [0u8; 8];
+    sighash.copy_from_slice(&hash[..8]);
+    sighash
+}
+
+fn generate_cpi_function_generic(instruction: &Instruction, discriminator: &[u8], idl: &Idl, input: &DeclareInput) -> syn::Result<proc_macro2::TokenStream> {
+    // the const and struct names below are derived from it, so it has to be usable as an identifier
+    function_ident(&instruction.name)?;
+    let discriminator_name = Ident::new(
+        &format!("{}_DISCRIMINATOR", to_snake_case(&instruction.name).to_uppercase()),
+        proc_macro2::Span::call_site(),
+    );
+    let discriminator_len = discriminator.len();
+    
+    for arg in instruction.args.iter().filter(|arg| !is_unresolved_type(&arg.arg_type, idl)) {
+        let subject = format!("Arg '{}.{}'", instruction.name, arg.name);
+        check_type_is_encodable(&arg.arg_type, idl, &subject)?;
+    }
+    if let Some(returns) = instruction.returns.as_ref().filter(|returns| !is_unresolved_type(returns, idl)) {
+        check_type_is_encodable(returns, idl, &format!("The return type of '{}'", instruction.name))?;
+    }
+    
+    // exact when every arg has a fixed size, otherwise the configured upper bound
+    let data_len = instruction.args
+        .iter()
+        .try_fold(discriminator_len, |len, arg| {
+            if is_unresolved_type(&arg.arg_type, idl) {
+                return None;
+            }
+            len.checked_add(arg.arg_type.max_param_len(idl)?)
+        })
+        .unwrap_or(input.max_data_len);
+    
+    let accounts_struct = generate_accounts_struct(instruction, idl, data_len, input)?;
+    let args_struct = generate_args_struct(instruction, &discriminator_name, idl, data_len)?;
+    let positional_function = if input.positional {
+        generate_positional_function(instruction, idl)?
+    } else {
+        quote! {}
+    };
+    
+    Ok(quote! {
+        pub const #discriminator_name: [u8; #discriminator_len] = [#(#discriminator),*];
+        
+        #accounts_struct
+        
+        #args_struct
+        
+        #positional_function
+    })
+}
+
+fn accounts_struct_ident(instruction: &Instruction) -> Ident {
+    Ident::new(&format!("{}Accounts", to_pascal_case(&instruction.name)), proc_macro2::Span::call_site())
+}
+
+fn args_struct_ident(instruction: &Instruction) -> Ident {
+    Ident::new(&format!("{}Args", to_pascal_case(&instruction.name)), proc_macro2::Span::call_site())
+}
 
-                    [0u8; 32]
+// the accounts of one instruction by name, and the CPI entry point
+fn generate_accounts_struct(instruction: &Instruction, idl: &Idl, data_len: usize, input: &DeclareInput) -> syn::Result<proc_macro2::TokenStream> {
+    let accounts_ident = accounts_struct_ident(instruction);
+    let args_ident = args_struct_ident(instruction);
+    let instruction_name = &instruction.name;
+    
+    let field_paths: Vec<_> = instruction.accounts
+        .iter()
+        .map(Account::field_path)
+        .collect::<syn::Result<_>>()?;
+    
+    let account_metas: Vec<_> = instruction.accounts
+        .iter()
+        .zip(&field_paths)
+        .map(|(account, field_path)| {
+            let key = if account.optional {
+                quote! { account.key() }
+            } else {
+                quote! { self.#field_path.key() }
+            };
+            let meta = match (account.is_mut, account.is_signer) {
+                (true, true) => quote! { AccountMeta::writable_signer(#key) },
+                (true, false) => quote! { AccountMeta::writable(#key) },
+                (false, true) => quote! { AccountMeta::readonly_signer(#key) },
+                (false, false) => quote! { AccountMeta::readonly(#key) },
+            };
+            // anchor reads the program id in an optional account's slot as "not provided"
+            if account.optional {
+                quote! {
+                    match self.#field_path {
+                        Some(account) => #meta,
+                        None => AccountMeta::readonly(&ID),
+                    }
                 }
+            } else {
+                meta
             }
+        })
+        .collect();
+    
+    let fixed_accounts: Vec<_> = instruction.accounts
+        .iter()
+        .zip(&field_paths)
+        .filter_map(|(account, field_path)| {
+            Some((account, field_path, addresses::address_constant(instruction, account, idl)?))
+        })
+        .collect();
+    
+    let address_checks: Vec<_> = fixed_accounts
+        .iter()
+        .map(|(account, field_path, constant)| {
+            let message = format!("{}: account `{}` does not match its fixed address", instruction_name, account.name);
+            let mismatch = if account.optional {
+                quote! { self.#field_path.is_some_and(|account| account.key() != &addresses::#constant) }
+            } else {
+                quote! { self.#field_path.key() != &addresses::#constant }
+            };
+            quote! {
+                if #mismatch {
+                    pinocchio::log::sol_log(#message);
+                    return Err(ProgramError::InvalidArgument);
+                }
+            }
+        })
+        .collect();
+    
+    let resolve_method = if fixed_accounts.is_empty() {
+        quote! {}
+    } else {
+        let given: Vec<_> = instruction.accounts
+            .iter()
+            .filter(|account| account.address.is_none())
+            .map(|account| {
+                let param_name = field_ident(&account.flat_name())?;
+                let param_type = account_param_type(account, &quote! { 'a });
+                Ok(quote! { #param_name: #param_type })
+            })
+            .collect::<syn::Result<_>>()?;
+        let accounts = instruction.accounts.iter().collect::<Vec<_>>();
+        let resolved = accounts_literal(&to_pascal_case(&instruction.name), &accounts, 0, &|account| {
+            match addresses::address_constant(instruction, account, idl) {
+                Some(constant) if account.optional => Ok(quote! { addresses::find_account(accounts, &addresses::#constant).ok() }),
+                Some(constant) => Ok(quote! { addresses::find_account(accounts, &addresses::#constant)? }),
+                None => {
+                    let param_name = field_ident(&account.flat_name())?;
+                    Ok(quote! { #param_name })
+                }
+            }
+        })?;
+        let fixed_list = fixed_accounts
+            .iter()
+            .map(|(account, _, _)| format!("`{}`", account.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let doc = format!("Takes every account except {}, which are looked up by address in `accounts`.", fixed_list);
+        quote! {
+            #[doc = #doc]
+            #[allow(clippy::too_many_arguments)]
+            pub fn resolve(#(#given,)* accounts: &'a [AccountInfo]) -> Result<Self, ProgramError> {
+                Ok(#resolved)
+            }
+        }
+    };
+    
+    let pda_checks = pda::generate_pda_checks(instruction, idl)?;
+    let (verify_method, verify_call) = if pda_checks.is_empty() {
+        (quote! {}, quote! {})
+    } else {
+        let verify_call = if input.verify_pdas {
+            quote! { self.verify_pdas(args)?; }
+        } else {
+            quote! {}
+        };
+        let verify_method = quote! {
+            /// Checks that every account derived from other accounts and args is at its PDA.
+            pub fn verify_pdas(&self, args: &#args_ident) -> ProgramResult {
+                #(#pda_checks)*
+                Ok(())
+            }
+        };
+        (verify_method, verify_call)
+    };
+    
+    let account_count = instruction.accounts.len();
+    let account_infos: Vec<_> = instruction.accounts
+        .iter()
+        .zip(&field_paths)
+        .map(|(account, field_path)| {
+            if account.optional {
+                quote! { self.#field_path }
+            } else {
+                quote! { Some(self.#field_path) }
+            }
+        })
+        .collect();
+    let account_infos = quote! {
+        let account_infos: [Option<&AccountInfo>; #account_count] = [#(#account_infos),*];
+    };
+    
+    let (result_type, read_return) = generate_return_data(instruction, idl)?;
+    
+    let invoke = if instruction.accounts.iter().any(|account| account.optional) {
+        let checked_invoke = generate_checked_invoke(quote! { account_infos.iter().copied() }, account_count);
+        quote! {
+            #account_infos
+            #checked_invoke
+        }
+    } else {
+        quote! {
+            invoke_signed(&instruction, &[#(self.#field_paths),*], signers)
         }
-        None => {
+    };
+    
+    // remaining accounts go after the declared ones, in fixed-size buffers sized for the configured maximum
+    let capacity = account_count + input.max_remaining_accounts;
+    let invoke_with_remaining = generate_checked_invoke(
+        quote! { account_infos.iter().copied().chain(remaining_accounts.iter().copied().map(Some)) },
+        capacity,
+    );
+    let invoke_with_remaining = quote! {
+        #account_infos
+        #invoke_with_remaining
+    };
+    
+    // the callee's return data is read once the CPI succeeded
+    let (invoke, invoke_with_remaining) = match read_return {
+        Some(read_return) => (
+            quote! {
+                let result: ProgramResult = { #invoke };
+                result?;
+                #read_return
+            },
             quote! {
-                /// program ID not specified 
-                pub const PROGRAM_ID: &str = "11111111111111111111111111111111";
+                let result: ProgramResult = { #invoke_with_remaining };
+                result?;
+                #read_return
+            },
+        ),
+        None => (invoke, invoke_with_remaining),
+    };
+    
+    let accounts: Vec<_> = instruction.accounts.iter().collect();
+    let struct_defs = generate_accounts_struct_defs(instruction, &to_pascal_case(&instruction.name), &accounts, 0)?;
+    
+    Ok(quote! {
+        #struct_defs
+        
+        impl<'a> #accounts_ident<'a> {
+            #resolve_method
+            
+            /// Metas of the instruction's accounts, in IDL order.
+            pub fn account_metas(&self) -> [AccountMeta<'a>; #account_count] {
+                [#(#account_metas),*]
+            }
+            
+            pub fn invoke(&self, args: &#args_ident) -> #result_type {
+                self.invoke_signed(args, &[])
+            }
+            
+            /// CPI with PDA signers, e.g. a vault PDA acting as an authority.
+            pub fn invoke_signed(&self, args: &#args_ident, signers: &[Signer]) -> #result_type {
+                #(#address_checks)*
+                #verify_call
+                let mut data = [0u8; #data_len];
+                let len = args.write_data(&mut data)?;
+                let instruction = Instruction {
+                    program_id: &ID,
+                    accounts: &self.account_metas(),
+                    data: &data[..len],
+                };
+                
+                #invoke
+            }
+            
+            /// CPI passing extra accounts after the IDL's, e.g. the signers of a multisig.
+            /// `remaining_metas` and `remaining_accounts` must list the same keys in the same order.
+            pub fn invoke_with_remaining(
+                &self,
+                args: &#args_ident,
+                remaining_metas: &[AccountMeta],
+                remaining_accounts: &[&AccountInfo],
+            ) -> #result_type {
+                self.invoke_signed_with_remaining(args, remaining_metas, remaining_accounts, &[])
+            }
+            
+            /// [`Self::invoke_with_remaining`] with PDA signers.
+            pub fn invoke_signed_with_remaining(
+                &self,
+                args: &#args_ident,
+                remaining_metas: &[AccountMeta],
+                remaining_accounts: &[&AccountInfo],
+                signers: &[Signer],
+            ) -> #result_type {
+                if remaining_metas.len() > MAX_REMAINING_ACCOUNTS {
+                    return Err(ProgramError::InvalidArgument);
+                }
+                if remaining_accounts.len() != remaining_metas.len() {
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                }
+                #(#address_checks)*
+                #verify_call
+                let mut data = [0u8; #data_len];
+                let len = args.write_data(&mut data)?;
                 
-                pub fn program_id() -> Pubkey {
-                    [0u8; 32] 
+                let declared = self.account_metas();
+                let meta_count = declared.len() + remaining_metas.len();
+                let mut metas: [AccountMeta; #capacity] = core::array::from_fn(|_| AccountMeta::readonly(&ID));
+                for (slot, meta) in metas.iter_mut().zip(declared.into_iter().chain(remaining_metas.iter().cloned())) {
+                    *slot = meta;
                 }
+                let instruction = Instruction {
+                    program_id: &ID,
+                    accounts: &metas[..meta_count],
+                    data: &data[..len],
+                };
+                
+                #invoke_with_remaining
             }
+            
+            #verify_method
         }
-    }
+    })
 }
 
-fn generate_cpi_function_generic(instruction: &Instruction, discriminant: u8) -> proc_macro2::TokenStream {
-    let function_name = syn::Ident::new(&instruction.name, proc_macro2::Span::call_site());
+fn generate_args_struct(instruction: &Instruction, discriminator_name: &Ident, idl: &Idl, data_len: usize) -> syn::Result<proc_macro2::TokenStream> {
+    let args_ident = args_struct_ident(instruction);
+    let instruction_name = &instruction.name;
+    let lifetime = quote! { 'a };
     
-    let account_params: Vec<_> = instruction.accounts
+    let borrows = instruction.args
         .iter()
-        .map(|account| {
-            let param_name = syn::Ident::new(&account.name, proc_macro2::Span::call_site());
-            quote! { #param_name: &AccountInfo }
+        .any(|arg| is_unresolved_type(&arg.arg_type, idl) || arg.arg_type.param_borrows());
+    let (generics, impl_generics) = if borrows {
+        (quote! { <'a> }, quote! { <'_> })
+    } else {
+        (quote! {}, quote! {})
+    };
+    
+    let fields: Vec<_> = instruction.args
+        .iter()
+        .map(|arg| {
+            let field_name = field_ident(&arg.name)?;
+            let field_type = generate_arg_param_type(&arg.arg_type, idl, &lifetime)?;
+            Ok(quote! { pub #field_name: #field_type })
         })
-        .collect();
+        .collect::<syn::Result<_>>()?;
     
-    let arg_params: Vec<_> = instruction.args
+    let arg_writes: Vec<_> = instruction.args
         .iter()
         .map(|arg| {
-            let param_name = syn::Ident::new(&arg.name, proc_macro2::Span::call_site());
-            quote! { #param_name: Vec<u8> }
+            let field_name = field_ident(&arg.name)?;
+            Ok(generate_arg_serialization(&arg.arg_type, &quote! { self.#field_name }, idl))
         })
-        .collect();
+        .collect::<syn::Result<_>>()?;
     
-    let all_params = [account_params, arg_params].concat();
+    let doc = format!("Arguments for the `{}` instruction.", instruction_name);
     
-    let account_metas: Vec<_> = instruction.accounts
-        .iter()
-        .map(|account| {
-            let param_name = syn::Ident::new(&account.name, proc_macro2::Span::call_site());
+    Ok(quote! {
+        #[doc = #doc]
+        pub struct #args_ident #generics {
+            #(#fields),*
+        }
+        
+        impl #args_ident #impl_generics {
+            /// Size of the stack buffer the instruction data is serialized into.
+            pub const MAX_DATA_LEN: usize = #data_len;
             
-            match (account.is_mut, account.is_signer) {
-                (true, true) => quote! { AccountMeta::writable_signer(#param_name.key()) },
-                (true, false) => quote! { AccountMeta::writable(#param_name.key()) },
-                (false, true) => quote! { AccountMeta::readonly_signer(#param_name.key()) },
-                (false, false) => quote! { AccountMeta::readonly(#param_name.key()) },
+            /// Writes the discriminator followed by the borsh encoded args to the front of `buf`,
+            /// returning the number of bytes written.
+            pub fn write_data(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                #[allow(unused_mut)]
+                let mut offset = codec::write_bytes(&#discriminator_name, buf)?;
+                #(#arg_writes)*
+                Ok(offset)
             }
-        })
-        .collect();
+            
+            /// Instruction data: the discriminator followed by the borsh encoded args.
+            pub fn data(&self) -> Result<codec::InstructionData<#data_len>, ProgramError> {
+                let mut buf = [0u8; #data_len];
+                let len = self.write_data(&mut buf)?;
+                Ok(codec::InstructionData::new(buf, len))
+            }
+        }
+    })
+}
+
+// the original flat signature: every account, then every arg, in IDL order
+fn generate_positional_function(instruction: &Instruction, idl: &Idl) -> syn::Result<proc_macro2::TokenStream> {
+    let function_name = function_ident(&instruction.name)?;
+    let signed_function_name = function_ident(&format!("{}_signed", instruction.name))?;
+    let args_ident = args_struct_ident(instruction);
     
-    let account_infos: Vec<_> = instruction.accounts
+    let account_params: Vec<_> = instruction.accounts
         .iter()
         .map(|account| {
-            let param_name = syn::Ident::new(&account.name, proc_macro2::Span::call_site());
-            quote! { #param_name }
+            let param_name = field_ident(&account.flat_name())?;
+            let param_type = account_param_type(account, &quote! {});
+            Ok(quote! { #param_name: #param_type })
         })
-        .collect();
-
-    let instruction_data = if instruction.args.is_empty() {
-        quote! { vec![#discriminant] }
-    } else {
-        let arg_names: Vec<_> = instruction.args
-            .iter()
-            .map(|arg| {
-                let param_name = syn::Ident::new(&arg.name, proc_macro2::Span::call_site());
-                quote! { &#param_name }
+        .collect::<syn::Result<_>>()?;
+    
+    let arg_params: Vec<_> = instruction.args
+        .iter()
+        .map(|arg| {
+            let param_name = field_ident(&arg.name)?;
+            let param_type = generate_arg_param_type(&arg.arg_type, idl, &quote! {})?;
+            Ok(quote! { #param_name: #param_type })
+        })
+        .collect::<syn::Result<_>>()?;
+    
+    let all_params = [account_params, arg_params].concat();
+    let (result_type, _) = generate_return_data(instruction, idl)?;
+    
+    let accounts: Vec<_> = instruction.accounts.iter().collect();
+    let accounts_value = accounts_literal(&to_pascal_case(&instruction.name), &accounts, 0, &|account| {
+        let param_name = field_ident(&account.flat_name())?;
+        Ok(quote! { #param_name })
+    })?;
+    
+    // params are named like the args struct fields
+    let arg_fields = instruction.args
+        .iter()
+        .map(|arg| field_ident(&arg.name))
+        .collect::<syn::Result<Vec<_>>>()?;
+    
+    Ok(quote! {
+        #[allow(clippy::too_many_arguments)]
+        pub fn #function_name(
+            #(#all_params),*
+        ) -> #result_type {
+            #accounts_value
+            .invoke(&#args_ident {
+                #(#arg_fields),*
             })
-            .collect();
+        }
         
-        quote! {
-            {
-                let mut data = vec![#discriminant];
-                #(data.extend(#arg_names);)*
-                data
+        #[allow(clippy::too_many_arguments)]
+        pub fn #signed_function_name(
+            #(#all_params,)*
+            signers: &[Signer]
+        ) -> #result_type {
+            #accounts_value
+            .invoke_signed(&#args_ident {
+                #(#arg_fields),*
+            }, signers)
+        }
+    })
+}
+
+// borrow-checks the provided account infos against the instruction's metas and invokes without copying them
+// into a slice of references, missing optional accounts are `None`
+fn generate_checked_invoke(account_infos: proc_macro2::TokenStream, capacity: usize) -> proc_macro2::TokenStream {
+    quote! {
+        let mut cpi_accounts = [const { core::mem::MaybeUninit::<pinocchio::instruction::Account>::uninit() }; #capacity];
+        let mut provided = 0;
+        for (account_info, meta) in #account_infos.zip(instruction.accounts) {
+            // the program id standing in for a missing account is executable and needs no account info
+            let Some(account_info) = account_info else {
+                continue;
+            };
+            if account_info.key() != meta.pubkey {
+                return Err(ProgramError::InvalidArgument);
+            }
+            let state = if meta.is_writable {
+                pinocchio::account_info::BorrowState::Borrowed
+            } else {
+                pinocchio::account_info::BorrowState::MutablyBorrowed
+            };
+            if account_info.is_borrowed(state) {
+                return Err(ProgramError::AccountBorrowFailed);
             }
+            cpi_accounts[provided].write(pinocchio::instruction::Account::from(account_info));
+            provided += 1;
         }
+        
+        // SAFETY: the first `provided` accounts are initialized and were checked for conflicting borrows
+        unsafe {
+            pinocchio::cpi::invoke_signed_unchecked(
+                &instruction,
+                core::slice::from_raw_parts(cpi_accounts.as_ptr() as *const pinocchio::instruction::Account, provided),
+                signers,
+            );
+        }
+        Ok(())
+    }
+}
+
+// the direct members of a struct nested `depth` groups deep, in IDL order
+enum AccountsEntry<'a> {
+    Account(&'a Account),
+    Group(&'a str, Vec<&'a Account>),
+}
+
+fn accounts_entries<'a>(accounts: &[&'a Account], depth: usize) -> Vec<AccountsEntry<'a>> {
+    let mut entries = Vec::new();
+    for &account in accounts {
+        match account.group.get(depth) {
+            None => entries.push(AccountsEntry::Account(account)),
+            Some(name) => match entries.last_mut() {
+                Some(AccountsEntry::Group(group, members)) if *group == name.as_str() => members.push(account),
+                _ => entries.push(AccountsEntry::Group(name, vec![account])),
+            },
+        }
+    }
+    entries
+}
+
+// `<prefix>Accounts` plus one struct per nested group, mirroring the program's composite `Accounts`
+fn generate_accounts_struct_defs(instruction: &Instruction, prefix: &str, accounts: &[&Account], depth: usize) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = quote::format_ident!("{}Accounts", prefix);
+    let mut fields = Vec::new();
+    let mut nested = Vec::new();
+    for entry in accounts_entries(accounts, depth) {
+        match entry {
+            AccountsEntry::Account(account) => {
+                let field_name = field_ident(&account.name)?;
+                let field_type = account_param_type(account, &quote! { 'a });
+                fields.push(quote! { pub #field_name: #field_type });
+            }
+            AccountsEntry::Group(name, members) => {
+                let group_prefix = format!("{}{}", prefix, to_pascal_case(name));
+                let field_name = field_ident(name)?;
+                let group_ident = quote::format_ident!("{}Accounts", group_prefix);
+                fields.push(quote! { pub #field_name: #group_ident<'a> });
+                nested.push(generate_accounts_struct_defs(instruction, &group_prefix, &members, depth + 1)?);
+            }
+        }
+    }
+    // an instruction without accounts still needs to use the lifetime
+    if accounts.is_empty() {
+        fields.push(quote! { pub _accounts: core::marker::PhantomData<&'a AccountInfo> });
+    }
+    
+    let doc = match accounts.first().and_then(|account| depth.checked_sub(1).map(|last| &account.group[last])) {
+        Some(group) => format!("Accounts of the `{}` group in the `{}` instruction.", group, instruction.name),
+        None => format!("Accounts for the `{}` instruction, in IDL order.", instruction.name),
     };
     
+    Ok(quote! {
+        #[doc = #doc]
+        pub struct #ident<'a> {
+            #(#fields),*
+        }
+        
+        #(#nested)*
+    })
+}
+
+// struct literal for `<prefix>Accounts`, taking each account's value from `value`
+fn accounts_literal(
+    prefix: &str,
+    accounts: &[&Account],
+    depth: usize,
+    value: &dyn Fn(&Account) -> syn::Result<proc_macro2::TokenStream>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = quote::format_ident!("{}Accounts", prefix);
+    let fields = accounts_entries(accounts, depth)
+        .into_iter()
+        .map(|entry| match entry {
+            AccountsEntry::Account(account) => {
+                let field_name = field_ident(&account.name)?;
+                let field_value = value(account)?;
+                Ok(quote! { #field_name: #field_value })
+            }
+            AccountsEntry::Group(name, members) => {
+                let field_name = field_ident(name)?;
+                let group_prefix = format!("{}{}", prefix, to_pascal_case(name));
+                let group_value = accounts_literal(&group_prefix, &members, depth + 1, value)?;
+                Ok(quote! { #field_name: #group_value })
+            }
+        })
+        .chain(accounts.is_empty().then(|| Ok(quote! { _accounts: core::marker::PhantomData })))
+        .collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! { #ident { #(#fields),* } })
+}
+
+// (CPI result type, expression reading the callee's return data), decoded into the `returns` type
+// when the IDL defines it and handed over raw otherwise
+fn generate_return_data(instruction: &Instruction, idl: &Idl) -> syn::Result<(proc_macro2::TokenStream, Option<proc_macro2::TokenStream>)> {
+    Ok(match &instruction.returns {
+        None => (quote! { ProgramResult }, None),
+        Some(returns) if is_unresolved_type(returns, idl) => (
+            quote! { Result<pinocchio::cpi::ReturnData, ProgramError> },
+            Some(quote! { codec::return_data(&ID) }),
+        ),
+        Some(returns) => {
+            let rust_type = returns.rust_type()?;
+            (quote! { Result<#rust_type, ProgramError> }, Some(quote! { codec::read_return_data(&ID) }))
+        }
+    })
+}
+
+fn account_param_type(account: &Account, lifetime: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if account.optional {
+        quote! { Option<&#lifetime AccountInfo> }
+    } else {
+        quote! { &#lifetime AccountInfo }
+    }
+}
+
+// defined types missing from the `types` section can't be generated, callers pass their serialized bytes
+fn is_unresolved_type(arg_type: &IdlType, idl: &Idl) -> bool {
+    arg_type
+        .defined_names()
+        .into_iter()
+        .any(|name| !idl.types.iter().any(|t| t.name == name))
+}
+
+fn generate_arg_param_type(arg_type: &IdlType, idl: &Idl, lifetime: &proc_macro2::TokenStream) -> syn::Result<proc_macro2::TokenStream> {
+    if is_unresolved_type(arg_type, idl) {
+        Ok(quote! { &#lifetime [u8] })
+    } else {
+        arg_type.param_type(lifetime)
+    }
+}
+
+// borsh encoding of a single arg written to `buf` at `offset`
+fn generate_arg_serialization(arg_type: &IdlType, value: &proc_macro2::TokenStream, idl: &Idl) -> proc_macro2::TokenStream {
+    if is_unresolved_type(arg_type, idl) {
+        return quote! { offset += codec::write_bytes(#value, &mut buf[offset..])?; };
+    }
+    
     quote! {
-        pub fn #function_name(
-            #(#all_params),*
-        ) -> ProgramResult {
-            let instruction = Instruction {
-                program_id: &program_id(),
-                accounts: &[
-                    #(#account_metas),*
-                ],
-                data: &#instruction_data,
+        offset += codec::BorshSerialize::serialize(&#value, &mut buf[offset..])?;
+    }
+}
+
+// "initializeMint" -> "InitializeMint", for generated type names
+fn to_pascal_case(name: &str) -> String {
+    to_snake_case(name)
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+// "setParams" -> "set_params", matching how anchor names things on chain
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+    
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            let next = chars.get(i + 1);
+            let starts_word = match prev {
+                Some('_') => false,
+                Some(p) if p.is_lowercase() || p.is_ascii_digit() => true,
+                Some(p) if p.is_uppercase() => next.is_some_and(|n| n.is_lowercase()),
+                _ => false,
             };
-            
-            invoke(&instruction, &[#(#account_infos),*])
+            if starts_word {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(*c);
+        }
+    }
+    
+    snake
+}
+
+// struct fields follow rust naming, keywords become raw identifiers
+fn field_ident(name: &str) -> syn::Result<Ident> {
+    checked_ident(&to_snake_case(name), name, "field or parameter")
+}
+
+// types, events, errors and variants keep the IDL's spelling
+fn type_ident(name: &str) -> syn::Result<Ident> {
+    checked_ident(name, name, "type")
+}
+
+// positional functions are named after the instruction as written
+fn function_ident(name: &str) -> syn::Result<Ident> {
+    checked_ident(name, name, "function")
+}
+
+// `ident` as a plain or raw identifier, `name` is the IDL spelling the error reports
+fn checked_ident(ident: &str, name: &str, kind: &str) -> syn::Result<Ident> {
+    if let Ok(ident) = syn::parse_str::<Ident>(ident) {
+        return Ok(ident);
+    }
+    // `self`, `super`, `crate` and `_` have no raw form, and names that aren't identifiers at all have none either
+    let unusable = matches!(ident, "self" | "super" | "crate" | "Self" | "_")
+        || syn::parse_str::<Ident>(&format!("{}_", ident)).is_err();
+    if unusable {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!("'{}' can't be used as a rust {} name", name, kind)
+        ));
+    }
+    Ok(Ident::new_raw(ident, proc_macro2::Span::call_site()))
+}
+
+// "$OUT_DIR/idl.json" or "idls/${PROGRAM}.json", relative paths are taken from the invoking crate's root
+// rather than rustc's working directory, which differs in workspaces
+fn resolve_idl_path(path: &LitStr) -> syn::Result<String> {
+    let expanded = expand_env_vars(&path.value()).map_err(|e| syn::Error::new(path.span(), e))?;
+    let expanded = std::path::Path::new(&expanded);
+    
+    let resolved = match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) if expanded.is_relative() => std::path::Path::new(&manifest_dir).join(expanded),
+        _ => expanded.to_path_buf(),
+    };
+    Ok(resolved.to_string_lossy().into_owned())
+}
+
+fn expand_env_vars(path: &str) -> Result<String, String> {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, remainder) = match after.strip_prefix('{') {
+            Some(braced) => {
+                let end = braced.find('}').ok_or_else(|| format!("Unclosed '${{' in idl_path '{}'", path))?;
+                (&braced[..end], &braced[end + 1..])
+            }
+            None => {
+                let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                after.split_at(end)
+            }
+        };
+        if name.is_empty() {
+            return Err(format!("Missing variable name after '$' in idl_path '{}'", path));
         }
+        let value = std::env::var(name)
+            .map_err(|_| format!("Environment variable '{}' used in idl_path '{}' is not set", name, path))?;
+        expanded.push_str(&value);
+        rest = remainder;
     }
+    expanded.push_str(rest);
+    
+    Ok(expanded)
 }
 
 struct DeclareInput {
     name: String,
-    id: Option<String>,
-    idl_path: String,
+    id: Option<LitStr>,
+    idl_source: IdlSource,
     idl_version: Option<u32>,  
+    override_address: bool,
+    discriminator: Option<DiscriminatorStrategy>,
+    positional: bool,
+    max_data_len: usize,
+    verify_pdas: bool,
+    max_remaining_accounts: usize,
+    only: Option<Vec<LitStr>>,
+    skip: Vec<LitStr>,
+}
+
+enum IdlSource {
+    // the `idl_path` literal for errors, and the path it resolves to, see `resolve_idl_path`
+    Path { literal: LitStr, resolved: String },
+    // JSON written in the invocation, kept as a literal to point errors at it
+    Inline(LitStr),
+}
+
+// solana's packet size, no instruction data can be larger
+const DEFAULT_MAX_DATA_LEN: usize = 1232;
+
+// room for the 11 signers of an SPL token multisig and then some
+const DEFAULT_MAX_REMAINING_ACCOUNTS: usize = 16;
+
+#[derive(Clone, Copy)]
+enum DiscriminatorStrategy {
+    AnchorSighash,
+    U8Index,
+    U32Index,
+    None,
+}
+
+impl syn::parse::Parse for DiscriminatorStrategy {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let value: LitStr = input.parse()?;
+        
+        match value.value().as_str() {
+            "anchor" => Ok(DiscriminatorStrategy::AnchorSighash),
+            "u8" => Ok(DiscriminatorStrategy::U8Index),
+            "u32" => Ok(DiscriminatorStrategy::U32Index),
+            "none" => Ok(DiscriminatorStrategy::None),
+            _ => Err(syn::Error::new_spanned(
+                value,
+                "Unknown discriminator. Expected 'anchor', 'u8', 'u32', or 'none'"
+            )),
+        }
+    }
 }
 
 impl syn::parse::Parse for DeclareInput {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let mut name = None;
         let mut id = None;
-        let mut idl_path = None;
+        let mut idl_source = None;
         let mut idl_version = None;
+        let mut override_address = false;
+        let mut discriminator = None;
+        let mut positional = true;
+        let mut max_data_len = DEFAULT_MAX_DATA_LEN;
+        let mut verify_pdas = false;
+        let mut max_remaining_accounts = DEFAULT_MAX_REMAINING_ACCOUNTS;
+        let mut only = None;
+        let mut skip = Vec::new();
         
         while !input.is_empty() {
             let key: Ident = input.parse()?;
@@ -193,19 +1096,55 @@ impl syn::parse::Parse for DeclareInput {
                 },
                 "id" => {
                     let value: LitStr = input.parse()?;
-                    id = Some(value.value());
+                    id = Some(value);
+                },
+                "idl_path" | "idl" | "idl_json" if idl_source.is_some() => {
+                    return Err(syn::Error::new_spanned(key, "Only one of 'idl_path' and 'idl' can be set"));
                 },
                 "idl_path" => {
-                    let value: LitStr = input.parse()?;
-                    idl_path = Some(value.value());
+                    let literal: LitStr = input.parse()?;
+                    let resolved = resolve_idl_path(&literal)?;
+                    idl_source = Some(IdlSource::Path { literal, resolved });
+                },
+                "idl" | "idl_json" => {
+                    idl_source = Some(IdlSource::Inline(input.parse()?));
                 },
                 "idl_version" => {
                     let value: syn::LitInt = input.parse()?;
                     idl_version = Some(value.base10_parse::<u32>()?);
                 },
+                "override_address" => {
+                    let value: syn::LitBool = input.parse()?;
+                    override_address = value.value;
+                },
+                "discriminator" => {
+                    discriminator = Some(input.parse::<DiscriminatorStrategy>()?);
+                },
+                "positional" => {
+                    let value: syn::LitBool = input.parse()?;
+                    positional = value.value;
+                },
+                "max_data_len" => {
+                    let value: syn::LitInt = input.parse()?;
+                    max_data_len = value.base10_parse::<usize>()?;
+                },
+                "verify_pdas" => {
+                    let value: syn::LitBool = input.parse()?;
+                    verify_pdas = value.value;
+                },
+                "max_remaining_accounts" => {
+                    let value: syn::LitInt = input.parse()?;
+                    max_remaining_accounts = value.base10_parse::<usize>()?;
+                },
+                "only" => {
+                    only = Some(parse_instruction_names(input)?);
+                },
+                "skip" => {
+                    skip = parse_instruction_names(input)?;
+                },
                 _ => return Err(syn::Error::new_spanned(
                     key, 
-                    "Unknown key. Expected 'name', 'id', 'idl_path', or 'idl_version'"
+                    "Unknown key. Expected 'name', 'id', 'idl_path', 'idl', 'idl_version', 'override_address', 'discriminator', 'positional', 'max_data_len', 'verify_pdas', 'max_remaining_accounts', 'only', or 'skip'"
                 )),
             }
             
@@ -217,17 +1156,43 @@ impl syn::parse::Parse for DeclareInput {
         Ok(DeclareInput {
             name: name.ok_or_else(|| syn::Error::new(input.span(), "Missing 'name' parameter"))?,
             id,
-            idl_path: idl_path.ok_or_else(|| syn::Error::new(input.span(), "Missing 'idl_path' parameter"))?,
-            idl_version: idl_version.or(Some(1)), // default to version 1
+            idl_source: idl_source.ok_or_else(|| syn::Error::new(input.span(), "Missing 'idl_path' or 'idl' parameter"))?,
+            idl_version, // detected from the file when not set
+            override_address,
+            discriminator,
+            positional,
+            max_data_len,
+            verify_pdas,
+            max_remaining_accounts,
+            only,
+            skip,
         })
     }
 }
 
+// `["transfer", "mintTo"]`
+fn parse_instruction_names(input: syn::parse::ParseStream) -> syn::Result<Vec<LitStr>> {
+    let content;
+    syn::bracketed!(content in input);
+    let names = content.parse_terminated(|name| name.parse::<LitStr>(), Token![,])?;
+    Ok(names.into_iter().collect())
+}
+
 
 #[derive(Debug, Deserialize, Serialize)]
 struct IdlV1 {
     name: String,
     instructions: Vec<Instruction>,
+    #[serde(default)]
+    accounts: Vec<IdlAccount>,
+    #[serde(default)]
+    types: Vec<IdlTypeDef>,
+    #[serde(default)]
+    events: Vec<IdlEvent>,
+    #[serde(default)]
+    errors: Vec<IdlErrorCode>,
+    #[serde(default)]
+    metadata: Option<IdlV1Metadata>,
     
     #[serde(flatten)]
     other_fields: HashMap<String, serde_json::Value>,
@@ -235,9 +1200,18 @@ struct IdlV1 {
 
 #[derive(Debug, Deserialize, Serialize)]
 struct IdlV2 {
-
+    #[serde(default)]
+    address: Option<String>,
     metadata: IdlMetadata,
     instructions: Vec<Instruction>,
+    #[serde(default)]
+    accounts: Vec<IdlAccount>,
+    #[serde(default)]
+    types: Vec<IdlTypeDef>,
+    #[serde(default)]
+    events: Vec<IdlEvent>,
+    #[serde(default)]
+    errors: Vec<IdlErrorCode>,
     
     #[serde(flatten)]
     other_fields: HashMap<String, serde_json::Value>,
@@ -246,7 +1220,23 @@ struct IdlV2 {
 #[derive(Debug)]
 struct Idl {
     name: String,
+    address: Option<String>,
     instructions: Vec<Instruction>,
+    accounts: Vec<IdlAccount>,
+    types: Vec<IdlTypeDef>,
+    events: Vec<IdlEvent>,
+    errors: Vec<IdlErrorCode>,
+    // written by anchor, which tags instructions, accounts and events with name hashes
+    anchor: bool,
+}
+
+// legacy anchor writes the deployed address here, shank adds its `origin` next to it
+#[derive(Debug, Deserialize, Serialize)]
+struct IdlV1Metadata {
+    #[serde(default)]
+    address: Option<String>,
+    #[serde(default)]
+    origin: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -259,9 +1249,17 @@ struct IdlMetadata {
 #[derive(Debug, Deserialize, Serialize)]
 struct Instruction {
     name: String,
+    #[serde(default)]
+    discriminator: Option<Vec<u8>>,
     accounts: Vec<Account>,
     #[serde(default)]  
     args: Vec<Arg>,
+    // what the program passes back through `set_return_data`
+    #[serde(default)]
+    returns: Option<IdlType>,
+    // position in the IDL, which index discriminators keep when other instructions are filtered out
+    #[serde(skip)]
+    index: usize,
     #[serde(flatten)]
     other_fields: std::collections::HashMap<String, serde_json::Value>,
 }
@@ -284,42 +1282,202 @@ struct Account {
     #[serde(alias = "signs")]
     is_signer: bool,
     
+    #[serde(default)]
+    #[serde(alias = "isOptional")]
+    optional: bool,
+    
+    #[serde(default)]
+    pda: Option<IdlPda>,
+    
+    #[serde(default)]
+    address: Option<String>,
+
+    // members of a composite `Accounts` group, flattened into the instruction after parsing
+    #[serde(default)]
+    accounts: Vec<Account>,
+
+    // names of the enclosing groups, outermost first
+    #[serde(skip)]
+    group: Vec<String>,
+
     #[serde(flatten)]
     other_fields: HashMap<String, serde_json::Value>,
 }
 
+impl Account {
+    // `self.<path>` of the account in its instruction's accounts struct, e.g. `curve.token_accounts.associated_user`
+    fn field_path(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let fields = self.group
+            .iter()
+            .chain(core::iter::once(&self.name))
+            .map(|name| field_ident(name))
+            .collect::<syn::Result<Vec<_>>>()?;
+        Ok(quote! { #(#fields).* })
+    }
+
+    // unique name across nested groups, e.g. `curve_tokenAccounts_associatedUser`
+    fn flat_name(&self) -> String {
+        self.group
+            .iter()
+            .chain(core::iter::once(&self.name))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+}
+
+
+#[derive(Debug, Deserialize, Serialize)]
+struct IdlAccount {
+    name: String,
+    #[serde(default)]
+    discriminator: Option<Vec<u8>>,
+    // legacy IDLs define the layout inline, v2 points at the `types` entry of the same name
+    #[serde(default)]
+    #[serde(rename = "type")]
+    ty: Option<IdlTypeDefTy>,
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Arg {
     name: String,
     #[serde(rename = "type")]
-    arg_type: serde_json::Value, 
+    arg_type: IdlType, 
 }
 
 fn read_idl_file(path: &str, version: Option<u32>) -> Result<Idl, Box<dyn std::error::Error>> {
     let content = std::fs::read_to_string(path)?;
+    parse_idl(&content, version)
+}
+
+fn parse_idl(content: &str, version: Option<u32>) -> Result<Idl, Box<dyn std::error::Error>> {
+    let json: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| format!("Invalid JSON: {}", e))?;
     
-    match version.unwrap_or(1) {
+    let (version, source) = match version {
+        Some(version) => (version, "set by 'idl_version'"),
+        None => detect_idl_version(&json),
+    };
+    
+    let mut idl = match version {
         1 => {
-            let idl_v1: IdlV1 = serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse as V1 IDL: {}", e))?;
+            let idl_v1: IdlV1 = serde_json::from_value(json)
+                .map_err(|e| format!("Failed to parse as V1 IDL ({}): {}", source, e))?;
             
-            Ok(Idl {
+            // a legacy anchor IDL only says so through the address `anchor deploy` writes
+            let anchor = idl_v1.metadata
+                .as_ref()
+                .is_some_and(|metadata| metadata.address.is_some() && metadata.origin.is_none());
+            Idl {
                 name: idl_v1.name,
+                address: idl_v1.metadata.and_then(|metadata| metadata.address),
                 instructions: idl_v1.instructions,
-            })
+                accounts: idl_v1.accounts,
+                types: idl_v1.types,
+                events: idl_v1.events,
+                errors: idl_v1.errors,
+                anchor,
+            }
         },
         2 => {
-            let idl_v2: IdlV2 = serde_json::from_str(&content)
-                .map_err(|e| format!("Failed to parse as V2 IDL: {}", e))?;
+            let idl_v2: IdlV2 = serde_json::from_value(json)
+                .map_err(|e| format!("Failed to parse as V2 IDL ({}): {}", source, e))?;
             
-            Ok(Idl {
+            Idl {
                 name: idl_v2.metadata.name,
+                address: idl_v2.address,
                 instructions: idl_v2.instructions,
-            })
+                accounts: idl_v2.accounts,
+                types: idl_v2.types,
+                events: idl_v2.events,
+                errors: idl_v2.errors,
+                anchor: true,
+            }
         },
         _ => {
-            Err(format!("Unsupported IDL version: {}. Supported versions: 1, 2", version.unwrap_or(1)).into())
+            return Err(format!("Unsupported IDL version: {}. Supported versions: 1, 2", version).into());
+        }
+    };
+    
+    inline_type_defs(&mut idl);
+    flatten_account_groups(&mut idl);
+    for (index, instruction) in idl.instructions.iter_mut().enumerate() {
+        instruction.index = index;
+    }
+    Ok(idl)
+}
+
+// v2 is anchor 0.30+, v1 covers legacy anchor and shank, which share a layout
+fn detect_idl_version(json: &serde_json::Value) -> (u32, &'static str) {
+    let metadata = json.get("metadata");
+    let instructions = json.get("instructions").and_then(serde_json::Value::as_array);
+    let instructions = instructions.map(Vec::as_slice).unwrap_or_default();
+    let accounts = instructions
+        .iter()
+        .filter_map(|instruction| instruction.get("accounts")?.as_array())
+        .flatten();
+    let has_account_key = |keys: &[&str]| accounts.clone().any(|account| keys.iter().any(|key| account.get(key).is_some()));
+    
+    if metadata.and_then(|metadata| metadata.get("origin")).and_then(serde_json::Value::as_str) == Some("shank") {
+        (1, "detected a shank IDL from `metadata.origin`")
+    } else if metadata.is_some_and(|metadata| metadata.get("spec").is_some()) {
+        (2, "detected an anchor 0.30+ IDL from `metadata.spec`")
+    } else if json.get("address").is_some() {
+        (2, "detected an anchor 0.30+ IDL from the top-level `address`")
+    } else if instructions.iter().any(|instruction| instruction.get("discriminator").is_some()) {
+        (2, "detected an anchor 0.30+ IDL from the instruction discriminators")
+    } else if has_account_key(&["isMut", "isSigner"]) {
+        (1, "detected a legacy anchor IDL from `isMut`/`isSigner`")
+    } else if has_account_key(&["writable", "signer"]) {
+        (2, "detected an anchor 0.30+ IDL from `writable`/`signer`")
+    } else {
+        (1, "no anchor 0.30+ markers found, assumed a legacy IDL")
+    }
+}
+
+// moves inline account and event layouts into `types`, so they get owned structs and a codec like any other type
+fn inline_type_defs(idl: &mut Idl) {
+    let accounts = idl.accounts
+        .iter_mut()
+        .filter_map(|account| Some((account.name.clone(), account.ty.take()?)));
+    let events = idl.events
+        .iter_mut()
+        .filter_map(|event| {
+            let fields = event.fields.take()?;
+            Some((event.name.clone(), IdlTypeDefTy::Struct { fields: Some(IdlDefinedFields::Named(fields)) }))
+        });
+    
+    for (name, ty) in accounts.chain(events).collect::<Vec<_>>() {
+        if !idl.types.iter().any(|type_def| type_def.name == name) {
+            idl.types.push(IdlTypeDef {
+                name,
+                docs: Vec::new(),
+                serialization: IdlSerialization::Borsh,
+                repr: None,
+                ty,
+            });
+        }
+    }
+}
+
+// composite groups become their members in IDL order, each remembering the groups it was nested in
+fn flatten_account_groups(idl: &mut Idl) {
+    fn flatten(accounts: Vec<Account>, group: &[String], flat: &mut Vec<Account>) {
+        for mut account in accounts {
+            if account.accounts.is_empty() {
+                account.group = group.to_vec();
+                flat.push(account);
+            } else {
+                let members = core::mem::take(&mut account.accounts);
+                let nested = [group, &[account.name]].concat();
+                flatten(members, &nested, flat);
+            }
         }
     }
-}
\ No newline at end of file
+    
+    for instruction in &mut idl.instructions {
+        let mut flat = Vec::with_capacity(instruction.accounts.len());
+        flatten(core::mem::take(&mut instruction.accounts), &[], &mut flat);
+        instruction.accounts = flat;
+    }
+}
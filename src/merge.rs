@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use syn::{ImplItem, Item, Type, UseTree};
+
+// generated items first, minus whatever the user defines again, then the user's items
+pub(crate) fn merge_items(generated: Vec<Item>, user: Vec<Item>) -> Vec<Item> {
+    let user_names: HashSet<String> = user
+        .iter()
+        .filter_map(item_name)
+        .chain(user.iter().flat_map(|item| match item {
+            Item::Use(item_use) => use_names(&item_use.tree),
+            _ => Vec::new(),
+        }))
+        .collect();
+    let user_methods: HashSet<(String, String)> = user
+        .iter()
+        .filter_map(|item| match item {
+            Item::Impl(item_impl) if item_impl.trait_.is_none() => Some(item_impl),
+            _ => None,
+        })
+        .flat_map(|item_impl| {
+            let self_ty = self_ty_name(&item_impl.self_ty);
+            item_impl.items.iter().filter_map(move |impl_item| match impl_item {
+                ImplItem::Fn(method) => Some((self_ty.clone()?, method.sig.ident.to_string())),
+                _ => None,
+            })
+        })
+        .collect();
+
+    let mut items: Vec<Item> = generated
+        .into_iter()
+        .filter_map(|item| match item {
+            // a type the user redefines takes its generated impls with it
+            Item::Impl(mut item_impl) => {
+                let self_ty = self_ty_name(&item_impl.self_ty);
+                if self_ty.as_ref().is_some_and(|self_ty| user_names.contains(self_ty)) {
+                    return None;
+                }
+                if item_impl.trait_.is_none() {
+                    item_impl.items.retain(|impl_item| match (impl_item, &self_ty) {
+                        (ImplItem::Fn(method), Some(self_ty)) => {
+                            !user_methods.contains(&(self_ty.clone(), method.sig.ident.to_string()))
+                        }
+                        _ => true,
+                    });
+                }
+                Some(Item::Impl(item_impl))
+            }
+            Item::Use(mut item_use) => {
+                item_use.tree = prune_use_tree(item_use.tree, &user_names)?;
+                Some(Item::Use(item_use))
+            }
+            item => match item_name(&item) {
+                Some(name) if user_names.contains(&name) => None,
+                _ => Some(item),
+            },
+        })
+        .collect();
+
+    items.extend(user);
+    items
+}
+
+fn item_name(item: &Item) -> Option<String> {
+    let ident = match item {
+        Item::Const(item) => &item.ident,
+        Item::Enum(item) => &item.ident,
+        Item::ExternCrate(item) => item.rename.as_ref().map_or(&item.ident, |(_, rename)| rename),
+        Item::Fn(item) => &item.sig.ident,
+        Item::Macro(item) => item.ident.as_ref()?,
+        Item::Mod(item) => &item.ident,
+        Item::Static(item) => &item.ident,
+        Item::Struct(item) => &item.ident,
+        Item::Trait(item) => &item.ident,
+        Item::Type(item) => &item.ident,
+        Item::Union(item) => &item.ident,
+        _ => return None,
+    };
+    Some(ident.to_string())
+}
+
+// `BuyAccounts` for `impl<'a> BuyAccounts<'a>`
+fn self_ty_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
+
+// names a `use` brings into scope, globs bring none that can clash
+fn use_names(tree: &UseTree) -> Vec<String> {
+    match tree {
+        UseTree::Path(path) => use_names(&path.tree),
+        UseTree::Name(name) => vec![name.ident.to_string()],
+        UseTree::Rename(rename) => vec![rename.rename.to_string()],
+        UseTree::Glob(_) => Vec::new(),
+        UseTree::Group(group) => group.items.iter().flat_map(use_names).collect(),
+    }
+}
+
+// drops the imports of names the user brings in or defines, `None` when nothing is left
+fn prune_use_tree(tree: UseTree, names: &HashSet<String>) -> Option<UseTree> {
+    match tree {
+        UseTree::Path(mut path) => {
+            *path.tree = prune_use_tree(*path.tree, names)?;
+            Some(UseTree::Path(path))
+        }
+        UseTree::Name(ref name) if names.contains(&name.ident.to_string()) => None,
+        UseTree::Rename(ref rename) if names.contains(&rename.rename.to_string()) => None,
+        UseTree::Group(mut group) => {
+            group.items = group.items
+                .into_iter()
+                .filter_map(|item| prune_use_tree(item, names))
+                .collect();
+            if group.items.is_empty() {
+                None
+            } else {
+                Some(UseTree::Group(group))
+            }
+        }
+        tree => Some(tree),
+    }
+}
@@ -0,0 +1,345 @@
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{IdlDefinedFields, IdlType, IdlTypeDefTy};
+use crate::{field_ident, to_snake_case, Idl, Instruction};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct IdlPda {
+    pub(crate) seeds: Vec<IdlSeed>,
+    // set when the PDA belongs to another program, e.g. an associated token account, legacy anchor says `programId`
+    #[serde(default, alias = "programId")]
+    pub(crate) program: Option<IdlSeed>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase", try_from = "RawIdlSeed")]
+pub(crate) enum IdlSeed {
+    Const {
+        value: Vec<u8>,
+    },
+    Arg {
+        path: String,
+    },
+    Account {
+        path: String,
+        // type of the account holding the seed, for paths like `bonding_curve.creator`
+        #[serde(default)]
+        account: Option<String>,
+    },
+}
+
+// v2 writes constant seeds as bytes, legacy anchor as a typed value, e.g. `{"type": "string", "value": "global"}`,
+// the other kinds carry a `type` too, which isn't needed
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum RawIdlSeed {
+    Const {
+        value: serde_json::Value,
+        #[serde(default, rename = "type")]
+        ty: Option<IdlType>,
+    },
+    Arg {
+        path: String,
+    },
+    Account {
+        path: String,
+        #[serde(default)]
+        account: Option<String>,
+    },
+}
+
+impl TryFrom<RawIdlSeed> for IdlSeed {
+    type Error = String;
+
+    fn try_from(seed: RawIdlSeed) -> Result<Self, Self::Error> {
+        match seed {
+            RawIdlSeed::Const { value, ty } => const_seed_bytes(&value, ty.as_ref()).map(|value| IdlSeed::Const { value }),
+            RawIdlSeed::Arg { path } => Ok(IdlSeed::Arg { path }),
+            RawIdlSeed::Account { path, account } => Ok(IdlSeed::Account { path, account }),
+        }
+    }
+}
+
+fn const_seed_bytes(value: &serde_json::Value, ty: Option<&IdlType>) -> Result<Vec<u8>, String> {
+    let invalid = || format!("unsupported const seed {}", value);
+    match (value, ty) {
+        (serde_json::Value::Array(bytes), _) => bytes
+            .iter()
+            .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()).ok_or_else(invalid))
+            .collect(),
+        (serde_json::Value::String(key), Some(IdlType::Pubkey)) => five8_const::try_decode_32_const(key)
+            .map(|key| key.to_vec())
+            .map_err(|_| format!("const seed '{}' is not a base58 encoded 32-byte pubkey", key)),
+        (serde_json::Value::String(text), _) => Ok(text.as_bytes().to_vec()),
+        (serde_json::Value::Number(number), Some(ty)) => {
+            let unsigned = || number.as_u64().ok_or_else(invalid);
+            let signed = || number.as_i64().ok_or_else(invalid);
+            let bytes = match ty {
+                IdlType::U8 => u8::try_from(unsigned()?).map_err(|_| invalid())?.to_le_bytes().to_vec(),
+                IdlType::U16 => u16::try_from(unsigned()?).map_err(|_| invalid())?.to_le_bytes().to_vec(),
+                IdlType::U32 => u32::try_from(unsigned()?).map_err(|_| invalid())?.to_le_bytes().to_vec(),
+                IdlType::U64 => unsigned()?.to_le_bytes().to_vec(),
+                IdlType::I8 => i8::try_from(signed()?).map_err(|_| invalid())?.to_le_bytes().to_vec(),
+                IdlType::I16 => i16::try_from(signed()?).map_err(|_| invalid())?.to_le_bytes().to_vec(),
+                IdlType::I32 => i32::try_from(signed()?).map_err(|_| invalid())?.to_le_bytes().to_vec(),
+                IdlType::I64 => signed()?.to_le_bytes().to_vec(),
+                _ => return Err(invalid()),
+            };
+            Ok(bytes)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+// one pair of helpers per PDA account name, prefixed with the instruction when instructions disagree on the seeds
+pub(crate) fn generate_pda_functions(idl: &Idl) -> syn::Result<TokenStream> {
+    let mut groups: Vec<(String, Vec<(&Instruction, &IdlPda)>)> = Vec::new();
+    for instruction in &idl.instructions {
+        for account in &instruction.accounts {
+            let Some(pda) = &account.pda else {
+                continue;
+            };
+            let name = to_snake_case(&account.name);
+            match groups.iter_mut().find(|(group_name, _)| *group_name == name) {
+                Some((_, pdas)) => pdas.push((instruction, pda)),
+                None => groups.push((name, vec![(instruction, pda)])),
+            }
+        }
+    }
+
+    let mut functions = Vec::new();
+    for (name, pdas) in &groups {
+        let (first_instruction, first_pda) = pdas[0];
+        if pdas.iter().all(|(_, pda)| *pda == first_pda) {
+            functions.push(generate_pda_pair(name, name, first_instruction, first_pda, idl)?);
+        } else {
+            for (instruction, pda) in pdas {
+                let prefixed = format!("{}_{}", to_snake_case(&instruction.name), name);
+                functions.push(generate_pda_pair(&prefixed, name, instruction, pda, idl)?);
+            }
+        }
+    }
+
+    Ok(quote! { #(#functions)* })
+}
+
+fn generate_pda_pair(function_name: &str, account_name: &str, instruction: &Instruction, pda: &IdlPda, idl: &Idl) -> syn::Result<TokenStream> {
+    let find_name = format_ident!("find_{}_address", function_name);
+    let create_name = format_ident!("create_{}_address", function_name);
+
+    let mut params: Vec<(Ident, TokenStream)> = Vec::new();
+    let seeds: Vec<_> = pda.seeds
+        .iter()
+        .map(|seed| seed_bytes(seed, instruction, idl, &mut params))
+        .collect::<syn::Result<_>>()?;
+    let seed_count = seeds.len();
+
+    let program = match &pda.program {
+        None => quote! { &ID },
+        Some(IdlSeed::Const { value }) if value.len() == 32 => quote! { &[#(#value),*] },
+        Some(IdlSeed::Const { value }) => {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("PDA program of account '{}' in IDL '{}' is {} bytes, expected a 32-byte pubkey",
+                       account_name,
+                       idl.name,
+                       value.len())
+            ));
+        }
+        Some(IdlSeed::Arg { path } | IdlSeed::Account { path, .. }) => {
+            let ident = seed_ident(path)?;
+            push_param(&mut params, ident.clone(), quote! { &Pubkey });
+            quote! { #ident }
+        }
+    };
+
+    let param_names = params.iter().map(|(name, _)| name);
+    let param_types = params.iter().map(|(_, ty)| ty);
+    let signature: Vec<_> = param_names.zip(param_types).map(|(name, ty)| quote! { #name: #ty }).collect();
+
+    let find_doc = format!("Finds the `{}` PDA and its bump.", account_name);
+    let create_doc = format!("Derives the `{}` PDA from a known bump.", account_name);
+    let bumped_count = seed_count + 1;
+
+    Ok(quote! {
+        #[doc = #find_doc]
+        pub fn #find_name(#(#signature),*) -> (Pubkey, u8) {
+            let seeds: [&[u8]; #seed_count] = [#(#seeds),*];
+            pinocchio::pubkey::find_program_address(&seeds, #program)
+        }
+
+        #[doc = #create_doc]
+        pub fn #create_name(#(#signature,)* bump: u8) -> Result<Pubkey, ProgramError> {
+            let seeds: [&[u8]; #bumped_count] = [#(#seeds,)* &[bump]];
+            pinocchio::pubkey::create_program_address(&seeds, #program)
+        }
+    })
+}
+
+// "bonding_curve.creator" -> bonding_curve_creator
+fn seed_ident(path: &str) -> syn::Result<Ident> {
+    field_ident(&path.replace('.', "_"))
+}
+
+fn push_param(params: &mut Vec<(Ident, TokenStream)>, name: Ident, ty: TokenStream) {
+    if !params.iter().any(|(existing, _)| *existing == name) {
+        params.push((name, ty));
+    }
+}
+
+// seed expression coercing to &[u8], registering a parameter for non-constant seeds
+fn seed_bytes(seed: &IdlSeed, instruction: &Instruction, idl: &Idl, params: &mut Vec<(Ident, TokenStream)>) -> syn::Result<TokenStream> {
+    let (path, ty) = match seed {
+        IdlSeed::Const { value } => {
+            let value = Literal::byte_string(value);
+            return Ok(quote! { #value });
+        }
+        IdlSeed::Arg { path } => (path, arg_seed_type(path, instruction, idl)),
+        IdlSeed::Account { path, account } => (path, account_seed_type(path, account.as_deref(), idl)),
+    };
+
+    let ident = seed_ident(path)?;
+    // anything else is passed as its raw seed bytes
+    let (param_type, bytes) = ty
+        .and_then(|ty| typed_seed(&ty, &quote! { #ident }))
+        .unwrap_or_else(|| (quote! { &[u8] }, quote! { #ident }));
+
+    push_param(params, ident, param_type);
+    Ok(bytes)
+}
+
+// (parameter type, bytes of `value`), the conversions work on both owned and borrowed values
+fn typed_seed(ty: &IdlType, value: &TokenStream) -> Option<(TokenStream, TokenStream)> {
+    match ty {
+        IdlType::Pubkey => Some((quote! { &Pubkey }, quote! { #value.as_slice() })),
+        IdlType::String => Some((quote! { &str }, quote! { #value.as_bytes() })),
+        IdlType::Bytes => Some((quote! { &[u8] }, quote! { &#value[..] })),
+        IdlType::Array(inner, len) if **inner == IdlType::U8 => Some((quote! { &[u8; #len] }, quote! { #value.as_slice() })),
+        IdlType::Bool => Some((quote! { bool }, quote! { &[#value as u8] })),
+        IdlType::U8 | IdlType::U16 | IdlType::U32 | IdlType::U64 | IdlType::U128
+        | IdlType::I8 | IdlType::I16 | IdlType::I32 | IdlType::I64 | IdlType::I128 => {
+            Some((ty.rust_type().ok()?, quote! { &#value.to_le_bytes() }))
+        }
+        _ => None,
+    }
+}
+
+// checks for every account whose seeds only use constants, other accounts' keys and args
+pub(crate) fn generate_pda_checks(instruction: &Instruction, idl: &Idl) -> syn::Result<Vec<TokenStream>> {
+    let mut checks = Vec::new();
+    for account in &instruction.accounts {
+        let Some(pda) = &account.pda else {
+            continue;
+        };
+        let Some(seeds) = pda.seeds
+            .iter()
+            .map(|seed| resolved_seed_bytes(seed, instruction, idl))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let seed_count = seeds.len();
+        let program = match &pda.program {
+            None => quote! { &ID },
+            Some(IdlSeed::Const { value }) if value.len() == 32 => quote! { &[#(#value),*] },
+            Some(IdlSeed::Const { .. }) => continue,
+            Some(seed) => match resolved_seed_value(seed, instruction) {
+                Some(key) => quote! { &#key },
+                None => continue,
+            },
+        };
+
+        let field_path = account.field_path()?;
+        let message = format!("{}: account `{}` does not match its PDA", instruction.name, account.name);
+        let check = quote! {
+            let seeds: [&[u8]; #seed_count] = [#(#seeds),*];
+            let program: &Pubkey = #program;
+            let expected = pinocchio::pubkey::try_find_program_address(&seeds, program).map(|(address, _)| address);
+            if expected.as_ref() != Some(account.key()) {
+                pinocchio::log::sol_log(#message);
+                return Err(ProgramError::InvalidSeeds);
+            }
+        };
+        // missing optional accounts have nothing to check
+        if account.optional {
+            checks.push(quote! { if let Some(account) = self.#field_path { #check } });
+        } else {
+            checks.push(quote! { { let account = self.#field_path; #check } });
+        }
+    }
+    Ok(checks)
+}
+
+fn resolved_seed_bytes(seed: &IdlSeed, instruction: &Instruction, idl: &Idl) -> Option<TokenStream> {
+    let ty = match seed {
+        IdlSeed::Const { value } => {
+            let value = Literal::byte_string(value);
+            return Some(quote! { #value });
+        }
+        IdlSeed::Arg { path } => arg_seed_type(path, instruction, idl)?,
+        IdlSeed::Account { .. } => IdlType::Pubkey,
+    };
+    let value = resolved_seed_value(seed, instruction)?;
+    typed_seed(&ty, &value).map(|(_, bytes)| bytes)
+}
+
+// `self.mint.key()` or `args.params.seed`, `None` for account data fields, optional accounts and unknown names,
+// names that aren't identifiers fail the accounts and args structs instead
+fn resolved_seed_value(seed: &IdlSeed, instruction: &Instruction) -> Option<TokenStream> {
+    match seed {
+        IdlSeed::Const { .. } => None,
+        IdlSeed::Account { path, .. } => {
+            // accounts inside composite groups are referenced as `group.account`
+            let account = instruction.accounts
+                .iter()
+                .find(|account| to_snake_case(&account.flat_name()) == to_snake_case(&path.replace('.', "_")) && !account.optional)?;
+            let field_path = account.field_path().ok()?;
+            Some(quote! { self.#field_path.key() })
+        }
+        IdlSeed::Arg { path } => {
+            let mut parts = path.split('.');
+            let arg_name = to_snake_case(parts.next()?);
+            let arg = instruction.args.iter().find(|arg| to_snake_case(&arg.name) == arg_name)?;
+            let fields = core::iter::once(arg.name.as_str())
+                .chain(parts)
+                .map(field_ident)
+                .collect::<syn::Result<Vec<_>>>()
+                .ok()?;
+            Some(quote! { args #(.#fields)* })
+        }
+    }
+}
+
+// "params.seed" walks into the fields of the `params` arg
+fn arg_seed_type(path: &str, instruction: &Instruction, idl: &Idl) -> Option<IdlType> {
+    let mut parts = path.split('.');
+    let arg_name = to_snake_case(parts.next()?);
+    let arg = instruction.args.iter().find(|arg| to_snake_case(&arg.name) == arg_name)?;
+    parts.try_fold(arg.arg_type.clone(), |ty, field| field_type(&ty, field, idl))
+}
+
+// a bare account path is its key, a dotted one reads a field of the account's data
+fn account_seed_type(path: &str, account: Option<&str>, idl: &Idl) -> Option<IdlType> {
+    let mut parts = path.split('.').skip(1).peekable();
+    if parts.peek().is_none() {
+        return Some(IdlType::Pubkey);
+    }
+    parts.try_fold(IdlType::Defined(account?.to_string()), |ty, field| field_type(&ty, field, idl))
+}
+
+fn field_type(ty: &IdlType, field: &str, idl: &Idl) -> Option<IdlType> {
+    let IdlType::Defined(name) = ty else {
+        return None;
+    };
+    let type_def = idl.types.iter().find(|type_def| type_def.name == *name)?;
+    match &type_def.ty {
+        IdlTypeDefTy::Struct { fields: Some(IdlDefinedFields::Named(fields)) } => fields
+            .iter()
+            .find(|candidate| to_snake_case(&candidate.name) == to_snake_case(field))
+            .map(|candidate| candidate.ty.clone()),
+        IdlTypeDefTy::Alias { alias } => field_type(alias, field, idl),
+        _ => None,
+    }
+}
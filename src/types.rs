@@ -0,0 +1,697 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use serde::{Deserialize, Serialize};
+
+use crate::{field_ident, type_ident, Idl};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "serde_json::Value", into = "serde_json::Value")]
+pub(crate) enum IdlType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Pubkey,
+    String,
+    Bytes,
+    Option(Box<IdlType>),
+    // solana's C-compatible option: u32 tag, value always present
+    COption(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Tuple(Vec<IdlType>),
+    Defined(String),
+}
+
+impl TryFrom<serde_json::Value> for IdlType {
+    type Error = String;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        if let Some(primitive) = value.as_str() {
+            return match primitive {
+                "bool" => Ok(IdlType::Bool),
+                "u8" => Ok(IdlType::U8),
+                "u16" => Ok(IdlType::U16),
+                "u32" => Ok(IdlType::U32),
+                "u64" => Ok(IdlType::U64),
+                "u128" => Ok(IdlType::U128),
+                "i8" => Ok(IdlType::I8),
+                "i16" => Ok(IdlType::I16),
+                "i32" => Ok(IdlType::I32),
+                "i64" => Ok(IdlType::I64),
+                "i128" => Ok(IdlType::I128),
+                "f32" => Ok(IdlType::F32),
+                "f64" => Ok(IdlType::F64),
+                "pubkey" | "publicKey" => Ok(IdlType::Pubkey),
+                "string" => Ok(IdlType::String),
+                "bytes" => Ok(IdlType::Bytes),
+                _ => Err(format!("unknown IDL type '{}'", primitive)),
+            };
+        }
+
+        let object = value
+            .as_object()
+            .filter(|object| object.len() == 1)
+            .ok_or_else(|| format!("unsupported IDL type {}", value))?;
+        let (kind, inner) = object.iter().next().unwrap();
+
+        match kind.as_str() {
+            "option" => Ok(IdlType::Option(Box::new(IdlType::try_from(inner.clone())?))),
+            "coption" => Ok(IdlType::COption(Box::new(IdlType::try_from(inner.clone())?))),
+            "vec" => Ok(IdlType::Vec(Box::new(IdlType::try_from(inner.clone())?))),
+            "array" => match inner.as_array().map(Vec::as_slice) {
+                Some([element, len]) => {
+                    let len = len
+                        .as_u64()
+                        .ok_or_else(|| format!("array length must be a number, got {}", len))?;
+                    Ok(IdlType::Array(Box::new(IdlType::try_from(element.clone())?), len as usize))
+                }
+                _ => Err(format!("invalid array type {}", inner)),
+            },
+            "tuple" => inner
+                .as_array()
+                .ok_or_else(|| format!("invalid tuple type {}", inner))?
+                .iter()
+                .map(|element| IdlType::try_from(element.clone()))
+                .collect::<Result<Vec<_>, _>>()
+                .map(IdlType::Tuple),
+            // v1: {"defined": "Name"}
+            "defined" => match inner {
+                serde_json::Value::String(name) => Ok(parse_defined_name(name)),
+                // v2: {"defined": {"name": "Name", "generics": [...]}}
+                _ => inner
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .map(|name| IdlType::Defined(name.to_string()))
+                    .ok_or_else(|| format!("invalid defined type {}", inner)),
+            },
+            _ => Err(format!("unsupported IDL type {}", value)),
+        }
+    }
+}
+
+// legacy IDLs spell solana's option as a defined type, e.g. "COption<Pubkey>"
+fn parse_defined_name(name: &str) -> IdlType {
+    let inner = name
+        .strip_prefix("COption<")
+        .and_then(|rest| rest.strip_suffix('>'));
+
+    let inner_type = inner.and_then(|inner| {
+        let primitive = if inner == "Pubkey" { "pubkey" } else { inner };
+        IdlType::try_from(serde_json::Value::String(primitive.to_string())).ok()
+    });
+
+    match inner_type {
+        Some(inner_type) => IdlType::COption(Box::new(inner_type)),
+        None => IdlType::Defined(name.to_string()),
+    }
+}
+
+impl From<IdlType> for serde_json::Value {
+    fn from(idl_type: IdlType) -> Self {
+        let primitive = match idl_type {
+            IdlType::Bool => "bool",
+            IdlType::U8 => "u8",
+            IdlType::U16 => "u16",
+            IdlType::U32 => "u32",
+            IdlType::U64 => "u64",
+            IdlType::U128 => "u128",
+            IdlType::I8 => "i8",
+            IdlType::I16 => "i16",
+            IdlType::I32 => "i32",
+            IdlType::I64 => "i64",
+            IdlType::I128 => "i128",
+            IdlType::F32 => "f32",
+            IdlType::F64 => "f64",
+            IdlType::Pubkey => "pubkey",
+            IdlType::String => "string",
+            IdlType::Bytes => "bytes",
+            IdlType::Option(inner) => return serde_json::json!({ "option": serde_json::Value::from(*inner) }),
+            IdlType::COption(inner) => return serde_json::json!({ "coption": serde_json::Value::from(*inner) }),
+            IdlType::Vec(inner) => return serde_json::json!({ "vec": serde_json::Value::from(*inner) }),
+            IdlType::Array(inner, len) => return serde_json::json!({ "array": [serde_json::Value::from(*inner), len] }),
+            IdlType::Tuple(elements) => {
+                let elements: Vec<serde_json::Value> = elements.into_iter().map(serde_json::Value::from).collect();
+                return serde_json::json!({ "tuple": elements });
+            }
+            IdlType::Defined(name) => return serde_json::json!({ "defined": { "name": name } }),
+        };
+        serde_json::Value::String(primitive.to_string())
+    }
+}
+
+impl IdlType {
+    /// Owned rust type used for struct fields, paths relative to the program module.
+    pub(crate) fn rust_type(&self) -> syn::Result<TokenStream> {
+        Ok(match self {
+            IdlType::Bool => quote! { bool },
+            IdlType::U8 => quote! { u8 },
+            IdlType::U16 => quote! { u16 },
+            IdlType::U32 => quote! { u32 },
+            IdlType::U64 => quote! { u64 },
+            IdlType::U128 => quote! { u128 },
+            IdlType::I8 => quote! { i8 },
+            IdlType::I16 => quote! { i16 },
+            IdlType::I32 => quote! { i32 },
+            IdlType::I64 => quote! { i64 },
+            IdlType::I128 => quote! { i128 },
+            IdlType::F32 => quote! { f32 },
+            IdlType::F64 => quote! { f64 },
+            IdlType::Pubkey => quote! { Pubkey },
+            IdlType::String => quote! { alloc::string::String },
+            IdlType::Bytes => quote! { alloc::vec::Vec<u8> },
+            IdlType::Option(inner) => {
+                let inner = inner.rust_type()?;
+                quote! { Option<#inner> }
+            }
+            IdlType::COption(inner) => {
+                let inner = inner.rust_type()?;
+                quote! { codec::COption<#inner> }
+            }
+            IdlType::Vec(inner) => {
+                let inner = inner.rust_type()?;
+                quote! { alloc::vec::Vec<#inner> }
+            }
+            IdlType::Array(inner, len) => {
+                let inner = inner.rust_type()?;
+                quote! { [#inner; #len] }
+            }
+            IdlType::Tuple(elements) => {
+                let elements = elements.iter().map(IdlType::rust_type).collect::<syn::Result<Vec<_>>>()?;
+                quote! { (#(#elements,)*) }
+            }
+            IdlType::Defined(name) => {
+                let ident = type_ident(name)?;
+                quote! { types::#ident }
+            }
+        })
+    }
+
+    /// Borrowed rust type used for CPI parameters, so callers don't need to allocate.
+    /// `lifetime` is empty for function parameters and `'a` inside args structs.
+    pub(crate) fn param_type(&self, lifetime: &TokenStream) -> syn::Result<TokenStream> {
+        Ok(match self {
+            IdlType::Pubkey => quote! { &#lifetime Pubkey },
+            IdlType::String => quote! { &#lifetime str },
+            IdlType::Bytes => quote! { &#lifetime [u8] },
+            // spl token packs optional instruction args with a single byte tag,
+            // the u32 tag layout only applies to account state
+            IdlType::Option(inner) | IdlType::COption(inner) => {
+                let inner = inner.param_type(lifetime)?;
+                quote! { Option<#inner> }
+            }
+            IdlType::Vec(inner) => {
+                let inner = inner.rust_type()?;
+                quote! { &#lifetime [#inner] }
+            }
+            IdlType::Array(inner, len) => {
+                let inner = inner.rust_type()?;
+                quote! { &#lifetime [#inner; #len] }
+            }
+            _ => self.rust_type()?,
+        })
+    }
+
+    /// Whether `param_type` holds a reference.
+    pub(crate) fn param_borrows(&self) -> bool {
+        match self {
+            IdlType::Pubkey | IdlType::String | IdlType::Bytes | IdlType::Vec(_) | IdlType::Array(..) => true,
+            IdlType::Option(inner) | IdlType::COption(inner) => inner.param_borrows(),
+            _ => false,
+        }
+    }
+
+    /// Largest borsh encoding of `rust_type`, `None` when the size is unbounded
+    /// (strings, bytes, vecs, or types missing from the IDL).
+    pub(crate) fn max_encoded_len(&self, idl: &Idl) -> Option<usize> {
+        match self {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => Some(1),
+            IdlType::U16 | IdlType::I16 => Some(2),
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => Some(4),
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => Some(8),
+            IdlType::U128 | IdlType::I128 => Some(16),
+            IdlType::Pubkey => Some(32),
+            IdlType::String | IdlType::Bytes | IdlType::Vec(_) => None,
+            IdlType::Option(inner) => inner.max_encoded_len(idl)?.checked_add(1),
+            IdlType::COption(inner) => inner.max_encoded_len(idl)?.checked_add(4),
+            IdlType::Array(inner, len) => inner.max_encoded_len(idl)?.checked_mul(*len),
+            IdlType::Tuple(elements) => max_encoded_len_sum(elements.iter(), idl),
+            IdlType::Defined(name) => idl.types
+                .iter()
+                .find(|type_def| type_def.name == *name)?
+                .max_encoded_len(idl),
+        }
+    }
+
+    /// Exact borsh encoding size, `None` unless every value of the type encodes to the same length.
+    pub(crate) fn fixed_encoded_len(&self, idl: &Idl) -> Option<usize> {
+        match self {
+            IdlType::Option(_) => None,
+            IdlType::COption(inner) => inner.fixed_encoded_len(idl)?.checked_add(4),
+            IdlType::Array(inner, len) => inner.fixed_encoded_len(idl)?.checked_mul(*len),
+            IdlType::Tuple(elements) => fixed_encoded_len_sum(elements.iter(), idl),
+            IdlType::Defined(name) => idl.types
+                .iter()
+                .find(|type_def| type_def.name == *name)?
+                .fixed_encoded_len(idl),
+            _ => self.max_encoded_len(idl),
+        }
+    }
+
+    /// Largest encoding of `param_type`, where options carry a single byte tag.
+    pub(crate) fn max_param_len(&self, idl: &Idl) -> Option<usize> {
+        match self {
+            IdlType::Option(inner) | IdlType::COption(inner) => inner.max_param_len(idl)?.checked_add(1),
+            _ => self.max_encoded_len(idl),
+        }
+    }
+
+    /// Names of every defined type this type refers to.
+    pub(crate) fn defined_names(&self) -> Vec<&str> {
+        match self {
+            IdlType::Defined(name) => vec![name.as_str()],
+            IdlType::Option(inner) | IdlType::COption(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+                inner.defined_names()
+            }
+            IdlType::Tuple(elements) => elements.iter().flat_map(IdlType::defined_names).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct IdlTypeDef {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) docs: Vec<String>,
+    // v2 only, legacy IDLs don't say how zero-copy accounts are laid out
+    #[serde(default)]
+    pub(crate) serialization: IdlSerialization,
+    #[serde(default)]
+    pub(crate) repr: Option<IdlRepr>,
+    #[serde(rename = "type")]
+    pub(crate) ty: IdlTypeDefTy,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum IdlSerialization {
+    #[default]
+    Borsh,
+    Bytemuck,
+    BytemuckUnsafe,
+    Custom(String),
+}
+
+// the `#[repr]` of the type, which decides how bytemuck types are laid out
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(crate) enum IdlRepr {
+    Rust(IdlReprModifier),
+    C(IdlReprModifier),
+    Transparent,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct IdlReprModifier {
+    #[serde(default)]
+    pub(crate) packed: bool,
+    #[serde(default)]
+    pub(crate) align: Option<usize>,
+}
+
+impl IdlTypeDef {
+    // enums take the tag plus their largest variant
+    fn max_encoded_len(&self, idl: &Idl) -> Option<usize> {
+        match &self.ty {
+            IdlTypeDefTy::Struct { fields } => max_encoded_len_sum(defined_field_types(fields).into_iter(), idl),
+            IdlTypeDefTy::Enum { variants } => variants
+                .iter()
+                .map(|variant| max_encoded_len_sum(defined_field_types(&variant.fields).into_iter(), idl))
+                .try_fold(0, |max, len| len.map(|len| max.max(len)))?
+                .checked_add(1),
+            IdlTypeDefTy::Alias { alias } => alias.max_encoded_len(idl),
+        }
+    }
+
+    // enums only have a fixed size when every variant does and they all match
+    fn fixed_encoded_len(&self, idl: &Idl) -> Option<usize> {
+        match &self.ty {
+            IdlTypeDefTy::Struct { fields } => fixed_encoded_len_sum(defined_field_types(fields).into_iter(), idl),
+            IdlTypeDefTy::Enum { variants } => {
+                let mut lens = variants
+                    .iter()
+                    .map(|variant| fixed_encoded_len_sum(defined_field_types(&variant.fields).into_iter(), idl));
+                let first = lens.next().unwrap_or(Some(0))?;
+                lens.all(|len| len == Some(first)).then_some(first)?.checked_add(1)
+            }
+            IdlTypeDefTy::Alias { alias } => alias.fixed_encoded_len(idl),
+        }
+    }
+}
+
+fn fixed_encoded_len_sum<'a>(mut types: impl Iterator<Item = &'a IdlType>, idl: &Idl) -> Option<usize> {
+    types.try_fold(0usize, |total, ty| total.checked_add(ty.fixed_encoded_len(idl)?))
+}
+
+fn max_encoded_len_sum<'a>(mut types: impl Iterator<Item = &'a IdlType>, idl: &Idl) -> Option<usize> {
+    types.try_fold(0usize, |total, ty| total.checked_add(ty.max_encoded_len(idl)?))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub(crate) enum IdlTypeDefTy {
+    Struct {
+        #[serde(default)]
+        fields: Option<IdlDefinedFields>,
+    },
+    Enum {
+        variants: Vec<IdlEnumVariant>,
+    },
+    #[serde(rename = "type")]
+    Alias {
+        alias: IdlType,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub(crate) enum IdlDefinedFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct IdlField {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) docs: Vec<String>,
+    #[serde(rename = "type")]
+    pub(crate) ty: IdlType,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct IdlEnumVariant {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) fields: Option<IdlDefinedFields>,
+}
+
+pub(crate) fn generate_types_module(idl: &Idl) -> syn::Result<TokenStream> {
+    let type_defs = idl.types
+        .iter()
+        .map(|type_def| generate_type_def(type_def, idl))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        pub mod types {
+            #[allow(unused_imports)]
+            use super::{alloc, codec, types};
+            #[allow(unused_imports)]
+            use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+
+            #(#type_defs)*
+        }
+    })
+}
+
+// every `defined` reference must point at something in the `types` section
+fn check_type_is_known(idl_type: &IdlType, idl: &Idl, owner: &str) -> syn::Result<()> {
+    match idl_type.defined_names().into_iter().find(|name| !idl.types.iter().any(|t| t.name == *name)) {
+        Some(name) => Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!("Type '{}' used by '{}' is not defined in IDL '{}'", name, owner, idl.name),
+        )),
+        None => Ok(()),
+    }
+}
+
+// the codec has tuple impls up to this length
+const MAX_TUPLE_LEN: usize = 12;
+
+/// Rejects shapes the codec can't encode, `subject` names the IDL field so the error says what to fix.
+pub(crate) fn check_type_is_encodable(idl_type: &IdlType, idl: &Idl, subject: &str) -> syn::Result<()> {
+    let reason = match idl_type {
+        IdlType::COption(inner) if inner.fixed_encoded_len(idl).is_none() => {
+            let inner = match serde_json::Value::from((**inner).clone()) {
+                serde_json::Value::String(name) => name,
+                json => json.to_string(),
+            };
+            Some(format!("is a coption of '{}', which has no fixed size to zero fill when empty", inner))
+        }
+        IdlType::Tuple(elements) if elements.len() > MAX_TUPLE_LEN => Some(format!(
+            "is a tuple of {} elements, at most {} are supported",
+            elements.len(),
+            MAX_TUPLE_LEN,
+        )),
+        _ => None,
+    };
+    if let Some(reason) = reason {
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            format!("{} in IDL '{}' {}", subject, idl.name, reason),
+        ));
+    }
+
+    match idl_type {
+        IdlType::Option(inner) | IdlType::COption(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            check_type_is_encodable(inner, idl, subject)
+        }
+        IdlType::Tuple(elements) => elements.iter().try_for_each(|element| check_type_is_encodable(element, idl, subject)),
+        _ => Ok(()),
+    }
+}
+
+// field names, or positions for tuple fields, next to their types
+fn labeled_field_types(fields: &Option<IdlDefinedFields>) -> Vec<(String, &IdlType)> {
+    match fields {
+        Some(IdlDefinedFields::Named(named)) => named.iter().map(|field| (field.name.clone(), &field.ty)).collect(),
+        Some(IdlDefinedFields::Tuple(tuple)) => tuple.iter().enumerate().map(|(i, ty)| (i.to_string(), ty)).collect(),
+        None => Vec::new(),
+    }
+}
+
+fn generate_type_def(type_def: &IdlTypeDef, idl: &Idl) -> syn::Result<TokenStream> {
+    let name = type_ident(&type_def.name)?;
+    let docs = &type_def.docs;
+    let fixed_len = type_def
+        .fixed_encoded_len(idl)
+        .map(|len| quote! { const FIXED_LEN: Option<usize> = Some(#len); });
+
+    match &type_def.ty {
+        IdlTypeDefTy::Struct { fields } => {
+            for field_type in defined_field_types(fields) {
+                check_type_is_known(field_type, idl, &type_def.name)?;
+            }
+            for (field, field_type) in labeled_field_types(fields) {
+                check_type_is_encodable(field_type, idl, &format!("Field '{}.{}'", type_def.name, field))?;
+            }
+
+            let body = match fields {
+                Some(IdlDefinedFields::Named(named)) => {
+                    let field_defs = named
+                        .iter()
+                        .map(|field| {
+                            let field_name = field_ident(&field.name)?;
+                            let field_type = field.ty.rust_type()?;
+                            let field_docs = &field.docs;
+                            Ok(quote! {
+                                #(#[doc = #field_docs])*
+                                pub #field_name: #field_type
+                            })
+                        })
+                        .collect::<syn::Result<Vec<_>>>()?;
+                    quote! { { #(#field_defs),* } }
+                }
+                Some(IdlDefinedFields::Tuple(tuple)) => {
+                    let field_types = tuple.iter().map(IdlType::rust_type).collect::<syn::Result<Vec<_>>>()?;
+                    quote! { ( #(pub #field_types),* ); }
+                }
+                None => quote! { ; },
+            };
+
+            let (bindings, pattern) = destructure_fields(fields, quote! { Self })?;
+            let construct = construct_fields(fields, quote! { Self })?;
+
+            Ok(quote! {
+                #(#[doc = #docs])*
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct #name #body
+
+                impl codec::BorshSerialize for #name {
+                    #fixed_len
+
+                    fn serialized_len(&self) -> usize {
+                        let #pattern = self;
+                        0 #(+ codec::BorshSerialize::serialized_len(#bindings))*
+                    }
+
+                    fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                        let #pattern = self;
+                        let mut offset = 0;
+                        #(offset += codec::BorshSerialize::serialize(#bindings, &mut buf[offset..])?;)*
+                        Ok(offset)
+                    }
+                }
+
+                impl codec::BorshDeserialize for #name {
+                    fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                        Ok(#construct)
+                    }
+                }
+            })
+        }
+        IdlTypeDefTy::Enum { variants } => {
+            for variant in variants {
+                for field_type in defined_field_types(&variant.fields) {
+                    check_type_is_known(field_type, idl, &type_def.name)?;
+                }
+                for (field, field_type) in labeled_field_types(&variant.fields) {
+                    let subject = format!("Field '{}::{}.{}'", type_def.name, variant.name, field);
+                    check_type_is_encodable(field_type, idl, &subject)?;
+                }
+            }
+
+            let variant_defs = variants
+                .iter()
+                .map(|variant| {
+                    let variant_name = type_ident(&variant.name)?;
+                    Ok(match &variant.fields {
+                        Some(IdlDefinedFields::Named(named)) => {
+                            let field_names = named
+                                .iter()
+                                .map(|field| field_ident(&field.name))
+                                .collect::<syn::Result<Vec<_>>>()?;
+                            let field_types = named
+                                .iter()
+                                .map(|field| field.ty.rust_type())
+                                .collect::<syn::Result<Vec<_>>>()?;
+                            quote! { #variant_name { #(#field_names: #field_types),* } }
+                        }
+                        Some(IdlDefinedFields::Tuple(tuple)) => {
+                            let field_types = tuple.iter().map(IdlType::rust_type).collect::<syn::Result<Vec<_>>>()?;
+                            quote! { #variant_name(#(#field_types),*) }
+                        }
+                        None => quote! { #variant_name },
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+
+            let mut len_arms = Vec::new();
+            let mut serialize_arms = Vec::new();
+            let mut deserialize_arms = Vec::new();
+            for (index, variant) in variants.iter().enumerate() {
+                let variant_name = type_ident(&variant.name)?;
+                let tag = index as u8;
+                let (bindings, pattern) = destructure_fields(&variant.fields, quote! { Self::#variant_name })?;
+                let construct = construct_fields(&variant.fields, quote! { Self::#variant_name })?;
+
+                len_arms.push(quote! {
+                    #pattern => 1 #(+ codec::BorshSerialize::serialized_len(#bindings))*
+                });
+                serialize_arms.push(quote! {
+                    #pattern => {
+                        let mut offset = codec::BorshSerialize::serialize(&#tag, buf)?;
+                        #(offset += codec::BorshSerialize::serialize(#bindings, &mut buf[offset..])?;)*
+                        Ok(offset)
+                    }
+                });
+                deserialize_arms.push(quote! { #tag => Ok(#construct) });
+            }
+
+            Ok(quote! {
+                #(#[doc = #docs])*
+                #[derive(Clone, Debug, PartialEq)]
+                pub enum #name {
+                    #(#variant_defs),*
+                }
+
+                impl codec::BorshSerialize for #name {
+                    #fixed_len
+
+                    fn serialized_len(&self) -> usize {
+                        match self {
+                            #(#len_arms),*
+                        }
+                    }
+
+                    fn serialize(&self, buf: &mut [u8]) -> Result<usize, ProgramError> {
+                        match self {
+                            #(#serialize_arms),*
+                        }
+                    }
+                }
+
+                impl codec::BorshDeserialize for #name {
+                    fn deserialize(data: &mut &[u8]) -> Result<Self, ProgramError> {
+                        match <u8 as codec::BorshDeserialize>::deserialize(data)? {
+                            #(#deserialize_arms,)*
+                            _ => Err(ProgramError::BorshIoError),
+                        }
+                    }
+                }
+            })
+        }
+        IdlTypeDefTy::Alias { alias } => {
+            check_type_is_known(alias, idl, &type_def.name)?;
+            check_type_is_encodable(alias, idl, &format!("Type '{}'", type_def.name))?;
+            let alias_type = alias.rust_type()?;
+
+            Ok(quote! {
+                #(#[doc = #docs])*
+                pub type #name = #alias_type;
+            })
+        }
+    }
+}
+
+pub(crate) fn defined_field_types(fields: &Option<IdlDefinedFields>) -> Vec<&IdlType> {
+    match fields {
+        Some(IdlDefinedFields::Named(named)) => named.iter().map(|field| &field.ty).collect(),
+        Some(IdlDefinedFields::Tuple(tuple)) => tuple.iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+// binds every field by reference: (`[a, b]`, `Path { a, b }`)
+fn destructure_fields(fields: &Option<IdlDefinedFields>, path: TokenStream) -> syn::Result<(Vec<proc_macro2::Ident>, TokenStream)> {
+    match fields {
+        Some(IdlDefinedFields::Named(named)) => {
+            let bindings = named.iter().map(|field| field_ident(&field.name)).collect::<syn::Result<Vec<_>>>()?;
+            let pattern = quote! { #path { #(#bindings),* } };
+            Ok((bindings, pattern))
+        }
+        Some(IdlDefinedFields::Tuple(tuple)) => {
+            let bindings: Vec<_> = (0..tuple.len())
+                .map(|i| quote::format_ident!("field_{}", i))
+                .collect();
+            let pattern = quote! { #path( #(#bindings),* ) };
+            Ok((bindings, pattern))
+        }
+        None => Ok((Vec::new(), path)),
+    }
+}
+
+// reads every field in declaration order
+fn construct_fields(fields: &Option<IdlDefinedFields>, path: TokenStream) -> syn::Result<TokenStream> {
+    match fields {
+        Some(IdlDefinedFields::Named(named)) => {
+            let field_names = named.iter().map(|field| field_ident(&field.name)).collect::<syn::Result<Vec<_>>>()?;
+            Ok(quote! { #path { #(#field_names: codec::BorshDeserialize::deserialize(data)?),* } })
+        }
+        Some(IdlDefinedFields::Tuple(tuple)) => {
+            let reads = tuple.iter().map(|_| quote! { codec::BorshDeserialize::deserialize(data)? });
+            Ok(quote! { #path( #(#reads),* ) })
+        }
+        None => Ok(path),
+    }
+}
@@ -15,7 +15,165 @@ declare_program! {
     idl_version = 2
 }
 
+// no id: the address embedded in the v2 IDL is used
+declare_program! {
+    name = "test_pump_idl_address",
+    idl_path = "tests/fixtures/pump_v2.json",
+    idl_version = 2
+}
+
+// legacy anchor IDL: discriminators computed from the instruction names
+declare_program! {
+    name = "test_pump_legacy",
+    idl_path = "tests/fixtures/pump_v1.json",
+    idl_version = 1,
+    discriminator = "anchor"
+}
+
+// no options: the address in its metadata marks the legacy IDL as anchor's, so names are hashed
+declare_program! {
+    name = "test_pump_v1",
+    idl_path = "tests/fixtures/pump_v1.json"
+}
+
+declare_program! {
+    name = "test_spl_u32",
+    id = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    idl_path = "tests/fixtures/spl_token.json",
+    idl_version = 1,
+    discriminator = "u32"
+}
+
+// struct based API only, with a smaller buffer for dynamically sized instruction data
+declare_program! {
+    name = "test_pump_structs",
+    idl_path = "tests/fixtures/pump_v2.json",
+    idl_version = 2,
+    positional = false,
+    max_data_len = 64
+}
+
+// PDA accounts checked against their seeds before every CPI
+declare_program! {
+    name = "test_pump_verified",
+    idl_path = "tests/fixtures/pump_v2.json",
+    idl_version = 2,
+    verify_pdas = true
+}
+
+// no idl_version: the format is sniffed from the file
+declare_program! {
+    name = "test_pump_detected",
+    idl_path = "tests/fixtures/pump_v2.json"
+}
+
+declare_program! {
+    name = "test_spl_detected",
+    id = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    idl_path = "tests/fixtures/spl_token.json"
+}
+
+// environment variables are expanded, relative paths start at the crate root
+declare_program! {
+    name = "test_spl_env_path",
+    id = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    idl_path = "${CARGO_MANIFEST_DIR}/tests/fixtures/spl_token.json"
+}
+
+// a hand-written interface, no JSON file needed
+declare_program! {
+    name = "test_counter",
+    id = "Counter111111111111111111111111111111111111",
+    idl = r#"{
+        "name": "counter",
+        "instructions": [
+            {
+                "name": "increment",
+                "accounts": [
+                    { "name": "counter", "isMut": true, "isSigner": false },
+                    { "name": "authority", "isMut": false, "isSigner": true }
+                ],
+                "args": [{ "name": "by", "type": "u64" }]
+            },
+            { "name": "ping", "accounts": [], "args": [] }
+        ]
+    }"#,
+    discriminator = "u8"
+}
+
+// rust keywords as instruction, type, variant, error, account and arg names become raw identifiers
+declare_program! {
+    name = "test_keywords",
+    id = "Counter111111111111111111111111111111111111",
+    idl = r#"{
+        "name": "keywords",
+        "instructions": [
+            {
+                "name": "configure",
+                "accounts": [{ "name": "match", "isMut": true, "isSigner": false }],
+                "args": [{ "name": "type", "type": "u8" }, { "name": "move", "type": "u16" }]
+            },
+            {
+                "name": "move",
+                "accounts": [{ "name": "match", "isMut": true, "isSigner": false }],
+                "args": [{ "name": "to", "type": { "defined": "loop" } }]
+            }
+        ],
+        "types": [
+            { "name": "loop", "type": { "kind": "enum", "variants": [{ "name": "dyn" }, { "name": "async" }] } }
+        ],
+        "errors": [{ "code": 6000, "name": "yield" }]
+    }"#
+}
+
+// attribute form: our own items live next to the generated ones and replace them by name
+#[sanity::program(id = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", idl_path = "tests/fixtures/spl_token.json")]
+pub mod spl_extended {
+    use pinocchio::program_error::ProgramError;
+
+    /// Rejects empty transfers instead of sending them.
+    pub fn transfer(source: &AccountInfo, destination: &AccountInfo, authority: &AccountInfo, amount: u64) -> ProgramResult {
+        if amount == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+        TransferAccounts { source, destination, authority }.invoke(&TransferArgs { amount })
+    }
+
+    pub fn transfer_twice(source: &AccountInfo, destination: &AccountInfo, authority: &AccountInfo, amount: u64) -> ProgramResult {
+        transfer(source, destination, authority, amount)?;
+        transfer(source, destination, authority, amount)
+    }
+
+    impl RevokeAccounts<'_> {
+        pub fn invoke(&self, _args: &RevokeArgs) -> ProgramResult {
+            Err(ProgramError::Custom(7))
+        }
+    }
+}
+
+// only the instructions a program uses, unreachable types and accounts are left out
+declare_program! {
+    name = "test_spl_only",
+    id = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    idl_path = "tests/fixtures/spl_token.json",
+    only = ["transfer", "mint_to", "setAuthority"]
+}
+
+declare_program! {
+    name = "test_pump_skip",
+    idl_path = "tests/fixtures/pump_v2.json",
+    skip = ["initialize", "setParams", "create", "withdraw"]
+}
+
+// composite types: option, coption, vec, array, tuple
+declare_program! {
+    name = "test_vault",
+    idl_path = "tests/fixtures/vault_v2.json",
+    idl_version = 2
+}
+
 #[cfg(test)]
+#[allow(clippy::type_complexity, clippy::assertions_on_constants, clippy::len_zero, dead_code)]
 mod macro_verification_tests {
     use super::*;
     use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
@@ -55,11 +213,11 @@ mod macro_verification_tests {
         
         
         // transfer: 3 accounts + 1 arg
-        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>) -> ProgramResult 
+        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult 
             = test_spl::transfer;
         
         // mintTo: 3 accounts + 1 arg  
-        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>) -> ProgramResult 
+        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult 
             = test_spl::mintTo;
         
         // revoke: 2 accounts + 0 args
@@ -67,7 +225,7 @@ mod macro_verification_tests {
             = test_spl::revoke;
         
         // initializeMint: 2 accounts + 3 args
-        let _: fn(&AccountInfo, &AccountInfo, Vec<u8>, Vec<u8>, Vec<u8>) -> ProgramResult 
+        let _: fn(&AccountInfo, &AccountInfo, u8, &Pubkey, Option<&Pubkey>) -> ProgramResult 
             = test_spl::initializeMint;
         
         println!("SPL Token functions generated with correct signatures");
@@ -78,7 +236,7 @@ mod macro_verification_tests {
             = test_pump::initialize;
         
         // buy: 12 accounts + 2 args
-        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>, Vec<u8>) -> ProgramResult 
+        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, u64, u64) -> ProgramResult 
             = test_pump::buy;
         
         // withdraw: 12 accounts + 0 args
@@ -101,7 +259,7 @@ mod macro_verification_tests {
         assert_eq!(test_pump::INSTRUCTION_COUNT, 6); 
         
         // both should generate functions
-        let _spl_fn: fn(&AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>) -> ProgramResult = test_spl::transfer;
+        let _spl_fn: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult = test_spl::transfer;
         let _pump_fn: fn(&AccountInfo, &AccountInfo, &AccountInfo) -> ProgramResult = test_pump::initialize;
         
         println!("V1 IDL parsed and generated correctly");
@@ -150,13 +308,13 @@ mod macro_verification_tests {
         // test to call functions (proves they compile)
         // we can't actually call them without real AccountInfo, but we can verify signatures
         
-        let transfer_fn = test_spl::transfer;
-        let mint_fn = test_spl::mintTo;
-        let init_fn = test_pump::initialize;
-        let buy_fn = test_pump::buy;
+        let _transfer_fn = test_spl::transfer;
+        let _mint_fn = test_spl::mintTo;
+        let _init_fn = test_pump::initialize;
+        let _buy_fn = test_pump::buy;
         
         // verify return types match expectations
-        let transfer_type = type_name::<fn(&AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>) -> ProgramResult>();
+        let transfer_type = type_name::<fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult>();
         let init_type = type_name::<fn(&AccountInfo, &AccountInfo, &AccountInfo) -> ProgramResult>();
         
         println!("transfer function type: {}", transfer_type);
@@ -171,15 +329,15 @@ mod macro_verification_tests {
         let _no_args: fn(&AccountInfo, &AccountInfo) -> ProgramResult = test_spl::revoke;
         let _no_args2: fn(&AccountInfo, &AccountInfo, &AccountInfo) -> ProgramResult = test_pump::initialize;
         
-        // functions with arguments should have Vec<u8> for each argument
-        let _one_arg: fn(&AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>) -> ProgramResult = test_spl::transfer;
-        let _three_args: fn(&AccountInfo, &AccountInfo, Vec<u8>, Vec<u8>, Vec<u8>) -> ProgramResult = test_spl::initializeMint;
-        let _two_args: fn(&AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>, Vec<u8>) -> ProgramResult = test_pump::buy;
+        // primitive arguments become real rust types
+        let _one_arg: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult = test_spl::transfer;
+        let _three_args: fn(&AccountInfo, &AccountInfo, u8, &Pubkey, Option<&Pubkey>) -> ProgramResult = test_spl::initializeMint;
+        let _two_args: fn(&AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, u64, u64) -> ProgramResult = test_pump::buy;
         
         println!("No-arg functions: correct signature");
         println!("Single-arg functions: correct signature");  
         println!("Multi-arg functions: correct signature");
-        println!("All arguments become Vec<u8> as expected");
+        println!("Primitive arguments map to rust types");
     }
 
     #[test]
@@ -189,7 +347,7 @@ mod macro_verification_tests {
         // count accounts by counting &AccountInfo params
         
         // transfer: 3 accounts (source, dest, authority)
-        let _transfer: fn(&AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>) -> ProgramResult = test_spl::transfer;
+        let _transfer: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult = test_spl::transfer;
         
         // revoke: 2 accounts (source, owner)
         let _revoke: fn(&AccountInfo, &AccountInfo) -> ProgramResult = test_spl::revoke;
@@ -198,7 +356,7 @@ mod macro_verification_tests {
         let _init: fn(&AccountInfo, &AccountInfo, &AccountInfo) -> ProgramResult = test_pump::initialize;
         
         // pump buy: 12 accounts (complex instruction)
-        let _buy: fn(&AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, Vec<u8>, Vec<u8>) -> ProgramResult = test_pump::buy;
+        let _buy: fn(&AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, u64, u64) -> ProgramResult = test_pump::buy;
         
         println!("Simple functions (2-3 accounts): correct");
         println!("Complex functions (12+ accounts): correct");
@@ -228,6 +386,1003 @@ mod macro_verification_tests {
         println!("Instruction counts match array lengths");
     }
 
+    #[test]
+    fn test_program_id_decoded_from_base58() {
+        println!("🔍 TESTING: Program id literal is decoded into real pubkey bytes");
+        
+        let spl_id: Pubkey = [
+            6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172,
+            28, 180, 133, 237, 95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
+        ];
+        let pump_id: Pubkey = [
+            1, 86, 224, 246, 147, 102, 90, 207, 68, 219, 21, 104, 191, 23, 91, 170,
+            81, 137, 203, 151, 245, 210, 255, 59, 101, 93, 43, 182, 253, 109, 24, 176,
+        ];
+        
+        assert_eq!(test_spl::ID, spl_id);
+        assert_eq!(test_pump::ID, pump_id);
+        assert_eq!(test_spl::program_id(), test_spl::ID);
+        assert_eq!(test_pump::program_id(), test_pump::ID);
+        
+        // usable in const context
+        const SPL_ID: Pubkey = test_spl::ID;
+        assert_ne!(SPL_ID, [0u8; 32]);
+        
+        println!("Program ids decoded at compile time");
+    }
+
+    #[test]
+    fn test_program_id_falls_back_to_idl_address() {
+        println!("🔍 TESTING: IDL address is used when id is omitted");
+        
+        assert_eq!(test_pump_idl_address::PROGRAM_ID, "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+        assert_eq!(test_pump_idl_address::ID, test_pump::ID);
+        
+        println!("IDL address used as program id");
+    }
+
+    // a signer, writable account serialized the way the runtime hands it to an entrypoint, read back by
+    // pinocchio's own deserializer; the returned buffer backs the account info and must outlive it
+    fn mock_account(key: Pubkey, owner: Pubkey, data: &[u8]) -> (Vec<u64>, AccountInfo) {
+        use pinocchio::account_info::MAX_PERMITTED_DATA_INCREASE;
+        
+        let mut input = 1u64.to_le_bytes().to_vec(); // account count
+        input.extend_from_slice(&[u8::MAX, 1, 1, 0]); // not a duplicate, signer, writable, not executable
+        input.extend_from_slice(&(data.len() as u32).to_le_bytes()); // original data len
+        input.extend_from_slice(&key);
+        input.extend_from_slice(&owner);
+        input.extend_from_slice(&0u64.to_le_bytes()); // lamports
+        input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        input.extend_from_slice(data);
+        // room for realloc, then the rent epoch at the next 8-byte boundary
+        input.resize((input.len() + MAX_PERMITTED_DATA_INCREASE).next_multiple_of(8), 0);
+        input.extend_from_slice(&0u64.to_le_bytes());
+        input.extend_from_slice(&0u64.to_le_bytes()); // instruction data len
+        input.extend_from_slice(&[0u8; 32]); // program id
+        
+        // u64 words keep the buffer as aligned as the runtime's
+        let mut buffer = vec![0u64; input.len().div_ceil(8)];
+        let bytes = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, input.len()) };
+        bytes.copy_from_slice(&input);
+        let mut accounts = [std::mem::MaybeUninit::<AccountInfo>::uninit()];
+        let (_, count, _) = unsafe { pinocchio::entrypoint::deserialize::<1>(buffer.as_mut_ptr() as *mut u8, &mut accounts) };
+        assert_eq!(count, 1);
+        let info = unsafe { accounts[0].assume_init_read() };
+        (buffer, info)
+    }
+    
+    // (key, writable, signer) of each meta, `AccountMeta` has no `PartialEq`
+    fn meta_flags(metas: &[pinocchio::instruction::AccountMeta]) -> Vec<(Pubkey, bool, bool)> {
+        metas.iter().map(|meta| (*meta.pubkey, meta.is_writable, meta.is_signer)).collect()
+    }
+
+    // buy's accounts in IDL order, fixed-address accounts at their address, plus the associated token program
+    fn pump_keys() -> Vec<Pubkey> {
+        use test_pump::addresses::*;
+        
+        let mut keys: Vec<Pubkey> = (0..7u8).map(|i| [i; 32]).collect();
+        keys.extend([SYSTEM_PROGRAM, TOKEN_PROGRAM, RENT, EVENT_AUTHORITY, PROGRAM, ASSOCIATED_TOKEN_PROGRAM]);
+        keys
+    }
+
+    #[test]
+    fn test_typed_arguments_can_be_passed_directly() {
+        println!("🔍 TESTING: Typed arguments are accepted by generated CPI functions");
+        
+        let (_src_buf, src) = mock_account([1u8; 32], test_spl::ID, &[]);
+        let (_dst_buf, dst) = mock_account([2u8; 32], test_spl::ID, &[]);
+        let (_auth_buf, auth) = mock_account([3u8; 32], [0u8; 32], &[]);
+        
+        // the positional fn sends the accounts struct's metas and the args struct's data
+        assert_eq!(test_spl::transfer(&src, &dst, &auth, 100u64), Ok(()));
+        let transfer = test_spl::TransferAccounts { source: &src, destination: &dst, authority: &auth };
+        assert_eq!(
+            meta_flags(&transfer.account_metas()),
+            vec![([1u8; 32], true, false), ([2u8; 32], true, false), ([3u8; 32], false, true)]
+        );
+        let mut transfer_data = vec![3u8];
+        transfer_data.extend_from_slice(&100u64.to_le_bytes());
+        assert_eq!(test_spl::TransferArgs { amount: 100 }.data().unwrap().as_slice(), transfer_data.as_slice());
+        
+        // legacy instructions tag COption with a single byte
+        assert_eq!(test_spl::initializeMint2(&src, 9u8, &[4u8; 32], None), Ok(()));
+        let mint_data = test_spl::InitializeMint2Args { decimals: 9, mint_authority: &[4u8; 32], freeze_authority: None }.data().unwrap();
+        assert_eq!(mint_data.as_slice(), [&test_spl::INITIALIZE_MINT2_DISCRIMINATOR[..], &[9], &[4u8; 32], &[0]].concat().as_slice());
+        
+        let (_global_buf, global) = mock_account([5u8; 32], test_pump::ID, &[]);
+        let (_user_buf, user) = mock_account([6u8; 32], [0u8; 32], &[]);
+        let (_system_buf, system_program) = mock_account(test_pump::addresses::SYSTEM_PROGRAM, [0u8; 32], &[]);
+        let (_authority_buf, event_authority) = mock_account(test_pump::addresses::EVENT_AUTHORITY, [0u8; 32], &[]);
+        let (_program_buf, program) = mock_account(test_pump::addresses::PROGRAM, [0u8; 32], &[]);
+        assert_eq!(test_pump::setParams(&global, &user, &system_program, &event_authority, &program, &[7u8; 32], 1u64, 2u64, 3u64, 4u64, 100u64), Ok(()));
+        let params = test_pump::SetParamsArgs {
+            fee_recipient: &[7u8; 32],
+            initial_virtual_token_reserves: 1,
+            initial_virtual_sol_reserves: 2,
+            initial_real_token_reserves: 3,
+            token_total_supply: 4,
+            fee_basis_points: 100,
+        };
+        let mut params_data = test_pump::SET_PARAMS_DISCRIMINATOR.to_vec();
+        params_data.extend_from_slice(&[7u8; 32]);
+        for value in [1u64, 2, 3, 4, 100] {
+            params_data.extend_from_slice(&value.to_le_bytes());
+        }
+        assert_eq!(params.data().unwrap().as_slice(), params_data.as_slice());
+        
+        println!("Typed arguments serialized and passed through CPI");
+    }
+
+    #[test]
+    fn test_instruction_discriminators() {
+        println!("🔍 TESTING: Instruction data is prefixed with the right discriminator");
+        
+        // v2 IDL: anchor discriminators taken verbatim
+        assert_eq!(test_pump::BUY_DISCRIMINATOR, [102, 6, 61, 18, 1, 218, 235, 234]);
+        assert_eq!(test_pump::SELL_DISCRIMINATOR, [51, 230, 133, 164, 1, 127, 131, 173]);
+        assert_eq!(test_pump::SET_PARAMS_DISCRIMINATOR, [165, 31, 134, 53, 189, 180, 130, 255]);
+        
+        // v1 IDL without discriminators: instruction index
+        assert_eq!(test_spl::TRANSFER_DISCRIMINATOR, [3]);
+        assert_eq!(test_spl::MINT_TO_DISCRIMINATOR, [7]);
+        assert_eq!(test_spl::INITIALIZE_ACCOUNT2_DISCRIMINATOR, [16]);
+        
+        println!("Discriminators generated correctly");
+    }
+
+    #[test]
+    fn test_discriminator_strategies() {
+        println!("🔍 TESTING: Discriminator strategy option");
+        
+        // sighashes computed for the legacy IDL match the ones the v2 IDL ships with
+        assert_eq!(test_pump_legacy::INITIALIZE_DISCRIMINATOR, test_pump::INITIALIZE_DISCRIMINATOR);
+        assert_eq!(test_pump_legacy::BUY_DISCRIMINATOR, test_pump::BUY_DISCRIMINATOR);
+        assert_eq!(test_pump_legacy::WITHDRAW_DISCRIMINATOR, test_pump::WITHDRAW_DISCRIMINATOR);
+        
+        // camelCase IDL names are hashed as the snake_case rust fn name: sha256("global:set_params")
+        assert_eq!(test_pump_legacy::SET_PARAMS_DISCRIMINATOR, [27, 234, 178, 52, 147, 2, 187, 141]);
+        
+        assert_eq!(test_spl_u32::TRANSFER_DISCRIMINATOR, [3, 0, 0, 0]);
+        assert_eq!(test_spl_u32::MINT_TO_DISCRIMINATOR, [7, 0, 0, 0]);
+        
+        println!("Anchor sighash and index discriminators generated correctly");
+    }
+
+    #[test]
+    fn test_legacy_anchor_discriminators_by_default() {
+        println!("🔍 TESTING: Legacy anchor IDLs get anchor discriminators without options");
+        
+        assert_eq!(test_pump_v1::INITIALIZE_DISCRIMINATOR, test_pump::INITIALIZE_DISCRIMINATOR);
+        assert_eq!(test_pump_v1::BUY_DISCRIMINATOR, test_pump::BUY_DISCRIMINATOR);
+        assert_eq!(test_pump_v1::WITHDRAW_DISCRIMINATOR, test_pump::WITHDRAW_DISCRIMINATOR);
+        
+        assert_eq!(test_pump_v1::accounts::BondingCurve::DISCRIMINATOR, test_pump::accounts::BondingCurve::DISCRIMINATOR);
+        assert_eq!(test_pump_v1::accounts::Global::DISCRIMINATOR, test_pump::accounts::Global::DISCRIMINATOR);
+        assert_eq!(test_pump_v1::events::TradeEvent::DISCRIMINATOR, test_pump::events::TradeEvent::DISCRIMINATOR);
+        
+        // a global account can't pass for a bonding curve
+        let mut global = test_pump_v1::accounts::Global::DISCRIMINATOR.to_vec();
+        global.resize(test_pump_v1::accounts::Global::LEN, 0);
+        assert!(global.len() >= test_pump_v1::accounts::BondingCurve::LEN);
+        let (_global_buf, global_info) = mock_account([4u8; 32], test_pump_v1::ID, &global);
+        assert!(test_pump_v1::accounts::Global::from_account_info(&global_info).is_ok());
+        assert_eq!(
+            test_pump_v1::accounts::BondingCurve::from_account_info(&global_info).err(),
+            Some(ProgramError::InvalidAccountData)
+        );
+        
+        // no anchor address in the metadata: a native program tagged by index, accounts untagged
+        assert_eq!(test_spl_detected::TRANSFER_DISCRIMINATOR, [3]);
+        assert_eq!(test_spl_detected::accounts::Mint::DISCRIMINATOR.len(), 0);
+        
+        println!("Legacy anchor IDL detected and hashed");
+    }
+
+    #[test]
+    fn test_types_generated_from_idl() {
+        use test_pump::codec::{BorshDeserialize, BorshSerialize};
+        println!("🔍 TESTING: Types section becomes rust structs and enums");
+        
+        let global = test_pump::types::Global {
+            initialized: true,
+            authority: [1u8; 32],
+            fee_recipient: [2u8; 32],
+            initial_virtual_token_reserves: 1_073_000_000_000_000,
+            initial_virtual_sol_reserves: 30_000_000_000,
+            initial_real_token_reserves: 793_100_000_000_000,
+            token_total_supply: 1_000_000_000_000_000,
+            fee_basis_points: 100,
+        };
+        
+        let mut buf = [0u8; 128];
+        let len = global.serialize(&mut buf).unwrap();
+        assert_eq!(len, 1 + 32 + 32 + 8 * 5);
+        assert_eq!(len, global.serialized_len());
+        assert_eq!(buf[0], 1);
+        assert_eq!(&buf[1..33], &[1u8; 32]);
+        assert_eq!(&buf[65..73], &1_073_000_000_000_000u64.to_le_bytes());
+        
+        let mut data = &buf[..len];
+        assert_eq!(test_pump::types::Global::deserialize(&mut data).unwrap(), global);
+        assert!(data.is_empty());
+        
+        // strings carry a u32 length prefix
+        let event = test_pump::types::CreateEvent {
+            name: "Sanity".to_string(),
+            symbol: "SNTY".to_string(),
+            uri: String::new(),
+            mint: [3u8; 32],
+            bonding_curve: [4u8; 32],
+            user: [5u8; 32],
+        };
+        let len = event.serialize(&mut buf).unwrap();
+        assert_eq!(&buf[..10], &[6, 0, 0, 0, b'S', b'a', b'n', b'i', b't', b'y']);
+        let mut data = &buf[..len];
+        assert_eq!(test_pump::types::CreateEvent::deserialize(&mut data).unwrap(), event);
+        
+        // enums are a u8 variant index, each program carries its own codec traits
+        use test_spl::codec::{BorshDeserialize as _, BorshSerialize as _};
+        let frozen = test_spl::types::AccountState::Frozen;
+        assert_eq!(frozen.serialize(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 2);
+        let mut data: &[u8] = &[1];
+        assert_eq!(test_spl::types::AccountState::deserialize(&mut data).unwrap(), test_spl::types::AccountState::Initialized);
+        let mut data: &[u8] = &[9];
+        assert!(test_spl::types::AccountState::deserialize(&mut data).is_err());
+        
+        // truncated input fails instead of panicking
+        let mut data: &[u8] = &[1, 2, 3];
+        assert!(test_pump::types::Global::deserialize(&mut data).is_err());
+        
+        // defined args take the generated type
+        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, test_spl::types::AuthorityType, Option<&Pubkey>) -> ProgramResult 
+            = test_spl::setAuthority;
+        
+        println!("Types generated with borsh encoding");
+    }
+
+    #[test]
+    fn test_composite_types() {
+        use test_vault::codec::{BorshDeserialize, BorshSerialize, COption};
+        use test_vault::types::{VaultMode, VaultParams};
+        println!("🔍 TESTING: Composite IDL types map to rust types and encodings");
+        
+        let params = VaultParams {
+            max_deposit: Some(500),
+            guardians: vec![[7u8; 32], [8u8; 32]],
+            seed: *b"sanity!!",
+            window: (-10, 10),
+            close_authority: COption(None),
+            mode: VaultMode::Locked { until: 42 },
+        };
+        
+        let mut buf = [0u8; 256];
+        let len = params.serialize(&mut buf).unwrap();
+        assert_eq!(len, params.serialized_len());
+        assert_eq!(len, (1 + 8) + (4 + 64) + 8 + 16 + (4 + 32) + (1 + 8));
+        
+        // option: u8 tag + value
+        assert_eq!(&buf[..9], &[1, 244, 1, 0, 0, 0, 0, 0, 0]);
+        // vec: u32 length + items
+        assert_eq!(&buf[9..13], &[2, 0, 0, 0]);
+        // coption: u32 tag, value zeroed but present
+        assert_eq!(&buf[101..137], &[0u8; 36]);
+        // enum with named fields
+        assert_eq!(&buf[137..146], &[1, 42, 0, 0, 0, 0, 0, 0, 0]);
+        
+        let mut data = &buf[..len];
+        assert_eq!(VaultParams::deserialize(&mut data).unwrap(), params);
+        
+        let with_authority = VaultParams { close_authority: COption(Some([9u8; 32])), mode: VaultMode::Migrating([1u8; 32]), ..params };
+        let len = with_authority.serialize(&mut buf).unwrap();
+        assert_eq!(&buf[101..105], &[1, 0, 0, 0]);
+        let mut data = &buf[..len];
+        assert_eq!(VaultParams::deserialize(&mut data).unwrap(), with_authority);
+        
+        // borrowed parameter types for args
+        let _: fn(&AccountInfo, &AccountInfo, Option<&AccountInfo>, VaultParams, Option<&Pubkey>, &[u64], &[u8; 4]) -> ProgramResult 
+            = test_vault::configure;
+        
+        println!("Composite types encoded correctly");
+    }
+
+    #[test]
+    fn test_coption_of_defined_type_and_long_tuple() {
+        use test_vault::codec::{BorshDeserialize, BorshSerialize, COption};
+        use test_vault::types::{Range, Schedule};
+        println!("🔍 TESTING: coption of a generated type and tuples past 6 elements");
+        
+        let schedule = Schedule { next_range: COption(None), steps: (1, 2, 3, 4, 5, 6, 7) };
+        assert_eq!(Schedule::FIXED_LEN, Some((4 + 4) + 7));
+        
+        // the empty coption still spans the zeroed range
+        let mut buf = [0xffu8; 32];
+        let len = schedule.serialize(&mut buf).unwrap();
+        assert_eq!(len, schedule.serialized_len());
+        assert_eq!(&buf[..len], &[0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7]);
+        let mut data = &buf[..len];
+        assert_eq!(Schedule::deserialize(&mut data).unwrap(), schedule);
+        
+        let schedule = Schedule { next_range: COption(Some(Range { lo: 1, hi: 2 })), ..schedule };
+        let len = schedule.serialize(&mut buf).unwrap();
+        assert_eq!(&buf[..8], &[1, 0, 0, 0, 1, 0, 2, 0]);
+        let mut data = &buf[..len];
+        assert_eq!(Schedule::deserialize(&mut data).unwrap(), schedule);
+        
+        println!("coption and long tuples encoded correctly");
+    }
+
+    #[test]
+    fn test_instruction_accounts_and_args_structs() {
+        println!("🔍 TESTING: Accounts and args structs per instruction");
+        
+        let keys = pump_keys();
+        let mocks: Vec<_> = keys.iter().map(|key| mock_account(*key, test_pump::ID, &[])).collect();
+        let infos: Vec<&AccountInfo> = mocks.iter().map(|(_, info)| info).collect();
+        
+        let accounts = test_pump::BuyAccounts {
+            global: infos[0],
+            fee_recipient: infos[1],
+            mint: infos[2],
+            bonding_curve: infos[3],
+            associated_bonding_curve: infos[4],
+            associated_user: infos[5],
+            user: infos[6],
+            system_program: infos[7],
+            token_program: infos[8],
+            rent: infos[9],
+            event_authority: infos[10],
+            program: infos[11],
+        };
+        let args = test_pump::BuyArgs { amount: 1_000, max_sol_cost: 5_000 };
+        
+        let data = args.data().unwrap();
+        assert_eq!(&data[..8], &test_pump::BUY_DISCRIMINATOR);
+        assert_eq!(&data[8..16], &1_000u64.to_le_bytes());
+        assert_eq!(&data[16..], &5_000u64.to_le_bytes());
+        assert_eq!(accounts.invoke(&args), Ok(()));
+        
+        // camelCase IDL names become snake_case fields
+        let sell = test_pump_structs::SellAccounts {
+            global: infos[0],
+            fee_recipient: infos[1],
+            mint: infos[2],
+            bonding_curve: infos[3],
+            associated_bonding_curve: infos[4],
+            associated_user: infos[5],
+            user: infos[6],
+            system_program: infos[7],
+            associated_token_program: infos[12],
+            token_program: infos[8],
+            event_authority: infos[10],
+            program: infos[11],
+        };
+        assert_eq!(sell.invoke(&test_pump_structs::SellArgs { amount: 1, min_sol_output: 0 }), Ok(()));
+        
+        // borrowed args
+        let create = test_pump::CreateArgs { name: "Sanity", symbol: "SNTY", uri: "", creator: &keys[1] };
+        let data = create.data().unwrap();
+        assert_eq!(data.len(), 8 + (4 + 6) + (4 + 4) + 4 + 32);
+        assert_eq!(&data[8..18], &[6, 0, 0, 0, b'S', b'a', b'n', b'i', b't', b'y']);
+        
+        assert_eq!(test_spl::TransferArgs { amount: 100 }.data().unwrap().as_slice(), [3, 100, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(test_spl::RevokeArgs {}.data().unwrap().as_slice(), [5]);
+        assert_eq!(
+            test_spl::InitializeMint2Args { decimals: 6, mint_authority: &keys[1], freeze_authority: None }.data().unwrap().len(),
+            1 + 1 + 32 + 1
+        );
+        
+        println!("Struct based CPI entry points generated");
+    }
+
+    #[test]
+    fn test_instruction_data_buffer_sizes() {
+        println!("🔍 TESTING: Stack buffer sizes for instruction data");
+        
+        // fixed size args get an exact buffer
+        assert_eq!(test_spl::TransferArgs::MAX_DATA_LEN, 1 + 8);
+        assert_eq!(test_spl::RevokeArgs::MAX_DATA_LEN, 1);
+        assert_eq!(test_spl_u32::RevokeArgs::MAX_DATA_LEN, 4);
+        assert_eq!(test_spl::InitializeMint2Args::MAX_DATA_LEN, 1 + 1 + 32 + 1 + 32);
+        assert_eq!(test_pump::BuyArgs::MAX_DATA_LEN, 8 + 8 + 8);
+        
+        // strings and vecs fall back to the configured bound
+        assert_eq!(test_pump::CreateArgs::MAX_DATA_LEN, 1232);
+        assert_eq!(test_vault::ConfigureArgs::MAX_DATA_LEN, 1232);
+        assert_eq!(test_pump_structs::CreateArgs::MAX_DATA_LEN, 64);
+        
+        let creator = [7u8; 32];
+        let short = test_pump_structs::CreateArgs { name: "a", symbol: "b", uri: "c", creator: &creator };
+        assert_eq!(short.data().unwrap().len(), 8 + 5 + 5 + 5 + 32);
+        
+        let long = test_pump_structs::CreateArgs { name: "a", symbol: "b", uri: "https://example.com/metadata.json", creator: &creator };
+        assert_eq!(long.data().err(), Some(ProgramError::BorshIoError));
+        
+        // callers can bring their own buffer
+        let mut buf = [0u8; 128];
+        let len = long.write_data(&mut buf).unwrap();
+        assert_eq!(&buf[..len], test_pump::CreateArgs { name: "a", symbol: "b", uri: "https://example.com/metadata.json", creator: &creator }.data().unwrap().as_slice());
+        
+        println!("Instruction data serialized without allocating");
+    }
+
+    #[test]
+    fn test_account_decoders() {
+        println!("🔍 TESTING: Zero-copy account views");
+        
+        // spl mint: no discriminator, coption authorities
+        let mut mint = vec![0u8; 82];
+        mint[0] = 1;
+        mint[4..36].copy_from_slice(&[9u8; 32]);
+        mint[36..44].copy_from_slice(&1_000_000u64.to_le_bytes());
+        mint[44] = 6;
+        mint[45] = 1;
+        let (_mint_buf, mint_info) = mock_account([1u8; 32], test_spl::ID, &mint);
+        
+        let view = test_spl::accounts::Mint::from_account_info(&mint_info).unwrap();
+        assert_eq!(test_spl::accounts::Mint::LEN, 82);
+        assert_eq!(view.mint_authority(), Some(&[9u8; 32]));
+        assert_eq!(view.supply(), 1_000_000);
+        assert_eq!(view.decimals(), 6);
+        assert!(view.is_initialized());
+        assert_eq!(view.freeze_authority(), None);
+        assert_eq!(view.decode().unwrap().supply, 1_000_000);
+        drop(view);
+        
+        let (_other_buf, other_owner) = mock_account([1u8; 32], [0u8; 32], &mint);
+        assert_eq!(test_spl::accounts::Mint::from_account_info(&other_owner).err(), Some(ProgramError::InvalidAccountOwner));
+        let (_short_buf, short) = mock_account([1u8; 32], test_spl::ID, &mint[..81]);
+        assert_eq!(test_spl::accounts::Mint::from_account_info(&short).err(), Some(ProgramError::InvalidAccountData));
+        
+        // enum fields are decoded on access
+        let mut token = vec![0u8; 165];
+        token[108] = 2;
+        let (_token_buf, token_info) = mock_account([2u8; 32], test_spl::ID, &token);
+        let view = test_spl::accounts::Account::from_account_info(&token_info).unwrap();
+        assert_eq!(test_spl::accounts::Account::LEN, 165);
+        assert_eq!(view.state(), Ok(test_spl::types::AccountState::Frozen));
+        assert_eq!(view.is_native(), None);
+        assert_eq!(test_spl::accounts::Multisig::LEN, 355);
+        
+        // v2 discriminators match the anchor hash computed for the legacy IDL
+        assert_eq!(test_pump::accounts::BondingCurve::DISCRIMINATOR, [23, 183, 248, 55, 96, 216, 172, 96]);
+        assert_eq!(test_pump_legacy::accounts::BondingCurve::DISCRIMINATOR, test_pump::accounts::BondingCurve::DISCRIMINATOR);
+        assert_eq!(test_pump_legacy::accounts::Global::DISCRIMINATOR, test_pump::accounts::Global::DISCRIMINATOR);
+        
+        let mut curve = test_pump::accounts::BondingCurve::DISCRIMINATOR.to_vec();
+        for reserve in [10u64, 20, 30, 40, 50] {
+            curve.extend_from_slice(&reserve.to_le_bytes());
+        }
+        curve.push(1);
+        let (_curve_buf, curve_info) = mock_account([3u8; 32], test_pump::ID, &curve);
+        let view = test_pump::accounts::BondingCurve::from_account_info(&curve_info).unwrap();
+        assert_eq!(view.virtual_token_reserves(), 10);
+        assert_eq!(view.token_total_supply(), 50);
+        assert!(view.complete());
+        assert_eq!(view.as_bytes(), curve.as_slice());
+        
+        let (_global_buf, global_info) = mock_account([4u8; 32], test_pump::ID, &curve);
+        assert_eq!(test_pump::accounts::Global::from_account_info(&global_info).err(), Some(ProgramError::InvalidAccountData));
+        
+        println!("Account views check owner and discriminator");
+    }
+
+    #[test]
+    fn test_zero_copy_account_layout() {
+        use test_vault::accounts::{PackedPosition, Position};
+        
+        println!("🔍 TESTING: bytemuck accounts read with their repr(C) layout");
+        
+        // flags at 0, padding to 8 for amount, u128 aligned to 8 on SBF, the nested struct after the pubkey,
+        // the whole struct padded to 72
+        assert_eq!(Position::LEN, 8 + 72);
+        let mut data = Position::DISCRIMINATOR.to_vec();
+        data.resize(Position::LEN, 0);
+        data[8] = 3;
+        data[16..24].copy_from_slice(&7u64.to_le_bytes());
+        data[24..40].copy_from_slice(&9u128.to_le_bytes());
+        data[40..72].copy_from_slice(&[5u8; 32]);
+        data[72..74].copy_from_slice(&11u16.to_le_bytes());
+        let (_position_buf, position_info) = mock_account([1u8; 32], test_vault::ID, &data);
+        let view = Position::from_account_info(&position_info).unwrap();
+        assert_eq!(view.flags(), 3);
+        assert_eq!(view.amount(), 7);
+        assert_eq!(view.total(), 9);
+        assert_eq!(view.owner(), &[5u8; 32]);
+        assert_eq!(&view.as_bytes()[72..74], &[11, 0]);
+        drop(view);
+        
+        // packed: no padding at all
+        assert_eq!(PackedPosition::LEN, 8 + 9);
+        let mut packed = PackedPosition::DISCRIMINATOR.to_vec();
+        packed.push(1);
+        packed.extend_from_slice(&42u64.to_le_bytes());
+        let (_packed_buf, packed_info) = mock_account([2u8; 32], test_vault::ID, &packed);
+        let view = PackedPosition::from_account_info(&packed_info).unwrap();
+        assert_eq!(view.flags(), 1);
+        assert_eq!(view.amount(), 42);
+        
+        println!("repr(C) padding and alignment respected");
+    }
+
+    #[test]
+    fn test_errors_generated_from_idl() {
+        use test_pump::errors::Error as PumpError;
+        use test_spl::errors::Error as TokenError;
+        
+        println!("🔍 TESTING: Typed program errors");
+        
+        assert_eq!(PumpError::NotAuthorized.code(), 6000);
+        assert_eq!(PumpError::BondingCurveComplete.code(), 6005);
+        assert_eq!(
+            PumpError::BondingCurveComplete.msg(),
+            "The bonding curve has completed and liquidity migrated to raydium."
+        );
+        assert_eq!(PumpError::try_from(6001), Ok(PumpError::AlreadyInitialized));
+        assert_eq!(PumpError::try_from(42), Err(42));
+        assert_eq!(ProgramError::from(PumpError::NotAuthorized), ProgramError::Custom(6000));
+        
+        // a failed CPI surfaces as ProgramError::Custom
+        assert_eq!(PumpError::from_program_error(&ProgramError::Custom(0x1775)), Some(PumpError::BondingCurveComplete));
+        assert_eq!(PumpError::from_program_error(&ProgramError::InvalidArgument), None);
+        assert_eq!(test_pump_legacy::errors::Error::try_from(6005), Ok(test_pump_legacy::errors::Error::BondingCurveComplete));
+        
+        assert_eq!(TokenError::NotRentExempt.code(), 0);
+        assert_eq!(TokenError::from_program_error(&ProgramError::Custom(1)), Some(TokenError::InsufficientFunds));
+        assert_eq!(TokenError::InsufficientFunds.to_string(), "Insufficient funds");
+        
+        // no errors section
+        assert_eq!(test_vault::errors::Error::try_from(6000), Err(6000));
+        
+        println!("Custom error codes map to named variants");
+    }
+
+    #[test]
+    fn test_event_decoding() {
+        use test_pump::events::{decode_event, Event, TradeEvent, EVENT_IX_TAG};
+        
+        println!("🔍 TESTING: Event decoding");
+        
+        let expected = TradeEvent {
+            mint: [1u8; 32],
+            sol_amount: 500,
+            token_amount: 1000,
+            is_buy: true,
+            user: [2u8; 32],
+            timestamp: 1_700_000_000,
+            virtual_sol_reserves: 10,
+            virtual_token_reserves: 20,
+            real_sol_reserves: 30,
+            real_token_reserves: 40,
+        };
+        
+        // emit!: a base64 log line
+        let log = "Program data: vdt/007mYe4BAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAfQBAAAAAAAA6AMAAAAAAAABAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgIA8VNlAAAAAAoAAAAAAAAAFAAAAAAAAAAeAAAAAAAAACgAAAAAAAAA";
+        assert_eq!(decode_event(log.as_bytes()), Ok(Event::TradeEvent(expected.clone())));
+        
+        // emit_cpi!: self-CPI instruction data
+        let mut raw = TradeEvent::DISCRIMINATOR.to_vec();
+        raw.extend_from_slice(&[1u8; 32]);
+        raw.extend_from_slice(&500u64.to_le_bytes());
+        raw.extend_from_slice(&1000u64.to_le_bytes());
+        raw.push(1);
+        raw.extend_from_slice(&[2u8; 32]);
+        for value in [1_700_000_000u64, 10, 20, 30, 40] {
+            raw.extend_from_slice(&value.to_le_bytes());
+        }
+        let cpi_data = [EVENT_IX_TAG.as_slice(), &raw].concat();
+        assert_eq!(decode_event(&cpi_data), Ok(Event::TradeEvent(expected.clone())));
+        assert_eq!(decode_event(&raw), Ok(Event::TradeEvent(expected)));
+        
+        // legacy IDLs hash the event name the same way
+        assert_eq!(test_pump_legacy::events::TradeEvent::DISCRIMINATOR, TradeEvent::DISCRIMINATOR);
+        assert_eq!(test_pump_legacy::events::CreateEvent::DISCRIMINATOR, test_pump::events::CreateEvent::DISCRIMINATOR);
+        
+        assert_eq!(decode_event(&[0u8; 16]), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(decode_event(&raw[..20]), Err(ProgramError::BorshIoError));
+        assert_eq!(decode_event(b"Program data: not*base64"), Err(ProgramError::InvalidInstructionData));
+        assert!(test_spl::events::decode_event(&raw).is_err());
+        
+        println!("Events decoded from logs and self-CPI data");
+    }
+
+    #[test]
+    fn test_pda_helpers_generated() {
+        println!("🔍 TESTING: PDA helpers from seeds");
+        
+        // const seeds only
+        let _: fn() -> (Pubkey, u8) = test_pump::find_global_address;
+        let _: fn(u8) -> Result<Pubkey, ProgramError> = test_pump::create_global_address;
+        let _: fn() -> (Pubkey, u8) = test_pump::find_mint_authority_address;
+        
+        // account seeds become key parameters
+        let _: fn(&Pubkey) -> (Pubkey, u8) = test_pump::find_bonding_curve_address;
+        let _: fn(&Pubkey, u8) -> Result<Pubkey, ProgramError> = test_pump::create_bonding_curve_address;
+        
+        // derived under the associated token program
+        let _: fn(&Pubkey, &Pubkey) -> (Pubkey, u8) = test_pump::find_associated_bonding_curve_address;
+        let _: fn(&Pubkey, &Pubkey) -> (Pubkey, u8) = test_pump::find_associated_user_address;
+        
+        // arg seeds take the arg's type, including fields of struct args
+        let _: fn(&Pubkey, &[u8; 8]) -> (Pubkey, u8) = test_vault::find_vault_address;
+        let _: fn(&Pubkey, &[u8; 8], u8) -> Result<Pubkey, ProgramError> = test_vault::create_vault_address;
+        
+        // legacy anchor seeds: typed const values and `programId`, the same helpers as the v2 bytes
+        let _: fn() -> (Pubkey, u8) = test_pump_v1::find_global_address;
+        let _: fn(&Pubkey) -> (Pubkey, u8) = test_pump_v1::find_bonding_curve_address;
+        let _: fn(&Pubkey, &Pubkey) -> (Pubkey, u8) = test_pump_v1::find_associated_bonding_curve_address;
+        
+        println!("PDA helpers generated for every seeded account");
+    }
+
+    #[test]
+    fn test_pda_verification() {
+        println!("🔍 TESTING: PDA verification before CPI");
+        
+        let keys = pump_keys();
+        let mocks: Vec<_> = keys.iter().map(|key| mock_account(*key, test_pump::ID, &[])).collect();
+        let infos: Vec<&AccountInfo> = mocks.iter().map(|(_, info)| info).collect();
+        
+        // PDA derivation is a syscall, off chain nothing matches
+        assert_eq!(
+            test_pump_verified::buy(infos[0], infos[1], infos[2], infos[3], infos[4], infos[5], infos[6], infos[7], infos[8], infos[9], infos[10], infos[11], 1, 1),
+            Err(ProgramError::InvalidSeeds)
+        );
+        
+        // without the option the check is available but not run
+        let accounts = test_pump::BuyAccounts {
+            global: infos[0],
+            fee_recipient: infos[1],
+            mint: infos[2],
+            bonding_curve: infos[3],
+            associated_bonding_curve: infos[4],
+            associated_user: infos[5],
+            user: infos[6],
+            system_program: infos[7],
+            token_program: infos[8],
+            rent: infos[9],
+            event_authority: infos[10],
+            program: infos[11],
+        };
+        let args = test_pump::BuyArgs { amount: 1, max_sol_cost: 1 };
+        assert_eq!(accounts.verify_pdas(&args), Err(ProgramError::InvalidSeeds));
+        assert_eq!(accounts.invoke(&args), Ok(()));
+        
+        // seeds from nested args
+        let vault = test_vault::ConfigureAccounts { vault: infos[0], authority: infos[1], guardian: None };
+        let params = test_vault::types::VaultParams {
+            max_deposit: None,
+            guardians: vec![],
+            seed: [1; 8],
+            window: (0, 0),
+            close_authority: None.into(),
+            mode: test_vault::types::VaultMode::Open,
+        };
+        let args = test_vault::ConfigureArgs { params, delegate: None, limits: &[], tag: &[0; 4] };
+        assert_eq!(vault.verify_pdas(&args), Err(ProgramError::InvalidSeeds));
+        
+        println!("PDA accounts verified before invoking");
+    }
+
+    #[test]
+    fn test_fixed_address_accounts() {
+        println!("🔍 TESTING: Fixed-address accounts");
+        
+        assert_eq!(test_pump::addresses::SYSTEM_PROGRAM, [0u8; 32]);
+        assert_eq!(test_pump::addresses::TOKEN_PROGRAM, test_spl::ID);
+        assert_eq!(test_pump::addresses::PROGRAM, test_pump::ID);
+        
+        let keys = pump_keys();
+        let mocks: Vec<_> = keys.iter().map(|key| mock_account(*key, test_pump::ID, &[])).collect();
+        let infos: Vec<&AccountInfo> = mocks.iter().map(|(_, info)| info).collect();
+        let (_wrong_buf, wrong) = mock_account([99u8; 32], [0u8; 32], &[]);
+        
+        // a substituted program account is rejected before the CPI
+        assert_eq!(
+            test_pump::buy(infos[0], infos[1], infos[2], infos[3], infos[4], infos[5], infos[6], infos[7], &wrong, infos[9], infos[10], infos[11], 1, 1),
+            Err(ProgramError::InvalidArgument)
+        );
+        
+        // the struct API looks fixed accounts up in the transaction's account list
+        let transaction_accounts: Vec<AccountInfo> = infos.iter().rev().map(|info| (*info).clone()).collect();
+        let sell = test_pump::SellAccounts::resolve(infos[0], infos[1], infos[2], infos[3], infos[4], infos[5], infos[6], &transaction_accounts).unwrap();
+        assert_eq!(sell.system_program.key(), &test_pump::addresses::SYSTEM_PROGRAM);
+        assert_eq!(sell.associated_token_program.key(), &test_pump::addresses::ASSOCIATED_TOKEN_PROGRAM);
+        assert_eq!(sell.invoke(&test_pump::SellArgs { amount: 1, min_sol_output: 0 }), Ok(()));
+        
+        assert_eq!(
+            test_pump::InitializeAccounts::resolve(infos[0], infos[6], &transaction_accounts[..3]).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+        
+        println!("Fixed addresses exposed, checked and resolved");
+    }
+
+    #[test]
+    fn test_optional_accounts() {
+        use test_vault::types::{VaultMode, VaultParams};
+        
+        println!("🔍 TESTING: Optional accounts");
+        
+        let (_vault_buf, vault) = mock_account([1u8; 32], test_vault::ID, &[]);
+        let (_authority_buf, authority) = mock_account([2u8; 32], [0u8; 32], &[]);
+        let (_guardian_buf, guardian) = mock_account([3u8; 32], [0u8; 32], &[]);
+        let params = || VaultParams {
+            max_deposit: None,
+            guardians: vec![],
+            seed: [0; 8],
+            window: (0, 0),
+            close_authority: None.into(),
+            mode: VaultMode::Open,
+        };
+        
+        // no dummy account needed when the guardian is absent
+        assert_eq!(test_vault::configure(&vault, &authority, None, params(), None, &[], &[0; 4]), Ok(()));
+        assert_eq!(test_vault::configure(&vault, &authority, Some(&guardian), params(), None, &[], &[0; 4]), Ok(()));
+        
+        let accounts = test_vault::ConfigureAccounts { vault: &vault, authority: &authority, guardian: None };
+        let args = test_vault::ConfigureArgs { params: params(), delegate: None, limits: &[], tag: &[0; 4] };
+        assert_eq!(accounts.invoke(&args), Ok(()));
+        
+        // borrows are still checked for the accounts that are passed
+        let _borrow = vault.try_borrow_mut_data().unwrap();
+        assert_eq!(accounts.invoke(&args), Err(ProgramError::AccountBorrowFailed));
+        
+        println!("Optional accounts can be left out");
+    }
+
+    #[test]
+    fn test_nested_account_groups() {
+        use test_pump_legacy::{WithdrawAccounts, WithdrawArgs, WithdrawCurveAccounts, WithdrawCurveTokenAccountsAccounts};
+
+        println!("🔍 TESTING: Composite account groups in legacy IDLs");
+
+        let buffers: Vec<_> = (0..12u8).map(|i| mock_account([i; 32], [0u8; 32], &[])).collect();
+        let infos: Vec<&AccountInfo> = buffers.iter().map(|(_, info)| info).collect();
+
+        // the groups are flattened: one parameter per member, none for the groups themselves
+        let _: fn(
+            &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo,
+            &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo, &AccountInfo,
+        ) -> ProgramResult = test_pump_legacy::withdraw;
+        assert_eq!(test_pump_legacy::withdraw(
+            infos[0], infos[1], infos[2], infos[3], infos[4], infos[5],
+            infos[6], infos[7], infos[8], infos[9], infos[10], infos[11],
+        ), Ok(()));
+
+        // the structs mirror the grouping, and the metas keep IDL order
+        let accounts = WithdrawAccounts {
+            global: infos[0],
+            last_withdraw: infos[1],
+            curve: WithdrawCurveAccounts {
+                mint: infos[2],
+                bonding_curve: infos[3],
+                token_accounts: WithdrawCurveTokenAccountsAccounts {
+                    associated_bonding_curve: infos[4],
+                    associated_user: infos[5],
+                },
+            },
+            user: infos[6],
+            system_program: infos[7],
+            token_program: infos[8],
+            rent: infos[9],
+            event_authority: infos[10],
+            program: infos[11],
+        };
+        assert_eq!(accounts.invoke(&WithdrawArgs {}), Ok(()));
+
+        println!("Nested account groups flattened in order");
+    }
+
+    #[test]
+    fn test_idl_format_detection() {
+        println!("🔍 TESTING: IDL format detected without idl_version");
+
+        // v2: discriminators and the address come from the file
+        assert_eq!(test_pump_detected::ID, test_pump::ID);
+        assert_eq!(test_pump_detected::INSTRUCTIONS, test_pump::INSTRUCTIONS);
+        assert_eq!(test_pump_detected::BUY_DISCRIMINATOR, test_pump::BUY_DISCRIMINATOR);
+        assert_eq!(test_pump_detected::accounts::Global::DISCRIMINATOR, test_pump::accounts::Global::DISCRIMINATOR);
+
+        // legacy: index discriminators like an explicit version 1
+        assert_eq!(test_spl_detected::INSTRUCTION_COUNT, test_spl::INSTRUCTION_COUNT);
+        assert_eq!(test_spl_detected::TRANSFER_DISCRIMINATOR, test_spl::TRANSFER_DISCRIMINATOR);
+        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult = test_spl_detected::transfer;
+
+        assert_eq!(test_spl_env_path::INSTRUCTIONS, test_spl::INSTRUCTIONS);
+
+        println!("IDL formats detected from their shape");
+    }
+
+    #[test]
+    fn test_inline_idl() {
+        println!("🔍 TESTING: IDL JSON written inside the macro invocation");
+
+        assert_eq!(test_counter::INSTRUCTIONS, &["increment", "ping"]);
+        assert_eq!(test_counter::INCREMENT_DISCRIMINATOR, [0]);
+        assert_eq!(test_counter::IncrementArgs { by: 2 }.data().unwrap().as_slice(), &[0, 2, 0, 0, 0, 0, 0, 0, 0]);
+
+        let (_counter_buf, counter) = mock_account([1u8; 32], test_counter::ID, &[]);
+        let (_authority_buf, authority) = mock_account([2u8; 32], [0u8; 32], &[]);
+        let accounts = test_counter::IncrementAccounts { counter: &counter, authority: &authority };
+        assert_eq!(meta_flags(&accounts.account_metas()), vec![([1u8; 32], true, false), ([2u8; 32], false, true)]);
+        assert_eq!(test_counter::increment(&counter, &authority, 1), Ok(()));
+
+        // no accounts: the struct only carries its lifetime
+        let ping = test_counter::PingAccounts { _accounts: core::marker::PhantomData };
+        assert_eq!(ping.account_metas().len(), 0);
+        assert_eq!(test_counter::PingArgs {}.data().unwrap().as_slice(), &[1]);
+        assert_eq!(test_counter::ping(), Ok(()));
+
+        println!("Inline IDL generates the same API as a file");
+    }
+
+    #[test]
+    fn test_keyword_names() {
+        println!("🔍 TESTING: Keywords in the IDL become raw identifiers");
+
+        let args = test_keywords::ConfigureArgs { r#type: 1, r#move: 2 };
+        assert_eq!(args.data().unwrap().as_slice(), &[0, 1, 2, 0]);
+
+        let (_match_buf, match_info) = mock_account([1u8; 32], test_keywords::ID, &[]);
+        let accounts = test_keywords::ConfigureAccounts { r#match: &match_info };
+        assert_eq!(accounts.r#match.key(), &[1u8; 32]);
+        let configure: fn(&AccountInfo, u8, u16) -> ProgramResult = test_keywords::configure;
+        assert_eq!(configure(&match_info, 1, 2), Ok(()));
+        
+        let args = test_keywords::MoveArgs { to: test_keywords::types::r#loop::r#async };
+        assert_eq!(args.data().unwrap().as_slice(), &[1, 1]);
+        let r#move: fn(&AccountInfo, test_keywords::types::r#loop) -> ProgramResult = test_keywords::r#move;
+        assert_eq!(r#move(&match_info, test_keywords::types::r#loop::r#async), Ok(()));
+        assert_eq!(test_keywords::errors::Error::r#yield.code(), 6000);
+
+        println!("Keyword names compile as fields and params");
+    }
+
+    #[test]
+    fn test_program_attribute() {
+        println!("🔍 TESTING: Attribute form merges user items into the generated module");
+
+        assert_eq!(spl_extended::MODULE_NAME, "spl_extended");
+        assert_eq!(spl_extended::INSTRUCTIONS, test_spl::INSTRUCTIONS);
+        assert_eq!(spl_extended::TRANSFER_DISCRIMINATOR, test_spl::TRANSFER_DISCRIMINATOR);
+
+        let (_src_buf, src) = mock_account([1u8; 32], spl_extended::ID, &[]);
+        let (_dst_buf, dst) = mock_account([2u8; 32], spl_extended::ID, &[]);
+        let (_auth_buf, auth) = mock_account([3u8; 32], [0u8; 32], &[]);
+
+        // the hand-written `transfer` replaces the generated one, the rest is untouched
+        assert_eq!(spl_extended::transfer(&src, &dst, &auth, 0), Err(ProgramError::InvalidArgument));
+        assert_eq!(spl_extended::transfer_twice(&src, &dst, &auth, 5), Ok(()));
+        assert_eq!(spl_extended::transfer_signed(&src, &dst, &auth, 0, &[]), Ok(()));
+
+        // methods can be replaced one at a time
+        let revoke = spl_extended::RevokeAccounts { source: &src, owner: &auth };
+        assert_eq!(revoke.invoke(&spl_extended::RevokeArgs {}), Err(ProgramError::Custom(7)));
+        assert_eq!(revoke.invoke_signed(&spl_extended::RevokeArgs {}, &[]), Ok(()));
+
+        println!("User items merged and override generated ones");
+    }
+
+    #[test]
+    fn test_instruction_filters() {
+        println!("🔍 TESTING: only/skip filters on instructions");
+
+        // IDL order and index discriminators survive the filter
+        assert_eq!(test_spl_only::INSTRUCTIONS, &["transfer", "setAuthority", "mintTo"]);
+        assert_eq!(test_spl_only::TRANSFER_DISCRIMINATOR, test_spl::TRANSFER_DISCRIMINATOR);
+        assert_eq!(test_spl_only::MINT_TO_DISCRIMINATOR, test_spl::MINT_TO_DISCRIMINATOR);
+        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64) -> ProgramResult = test_spl_only::mintTo;
+
+        // types used by kept instructions stay
+        let _ = test_spl_only::types::AuthorityType::MintTokens;
+        // every account layout stays with the types it reads, whichever instructions are kept
+        assert_eq!(test_spl_only::accounts::Mint::LEN, test_spl::accounts::Mint::LEN);
+        assert_eq!(test_spl_only::accounts::Multisig::LEN, test_spl::accounts::Multisig::LEN);
+        let _ = test_spl_only::types::AccountState::Frozen;
+        // so do all the errors, the IDL doesn't tie them to instructions
+        assert_eq!(test_spl_only::errors::Error::InvalidInstruction.code(), test_spl::errors::Error::InvalidInstruction.code());
+
+        assert_eq!(test_pump_skip::INSTRUCTIONS, &["buy", "sell"]);
+        assert_eq!(test_pump_skip::BUY_DISCRIMINATOR, test_pump::BUY_DISCRIMINATOR);
+        // pump's accounts and errors are all there
+        assert_eq!(test_pump_skip::accounts::BondingCurve::DISCRIMINATOR, test_pump::accounts::BondingCurve::DISCRIMINATOR);
+        assert_eq!(test_pump_skip::accounts::Global::DISCRIMINATOR, test_pump::accounts::Global::DISCRIMINATOR);
+        assert_eq!(test_pump_skip::errors::Error::try_from(6005), Ok(test_pump_skip::errors::Error::BondingCurveComplete));
+        let _ = test_pump_skip::find_bonding_curve_address;
+
+        println!("Instructions filtered with their unreachable types");
+    }
+
+    #[test]
+    fn test_return_data() {
+        use test_vault::types::Preview;
+
+        println!("🔍 TESTING: Return data decoded into the `returns` type");
+
+        // instructions with `returns` hand back the decoded value, the others stay ProgramResult
+        let _: fn(&AccountInfo, u64) -> Result<Preview, ProgramError> = test_vault::preview;
+        let _: fn(&AccountInfo) -> Result<u64, ProgramError> = test_vault::totalAssets;
+
+        // off-chain the runtime never sets return data
+        let (_vault_buf, vault) = mock_account([1u8; 32], test_vault::ID, &[]);
+        assert_eq!(test_vault::preview(&vault, 100), Err(ProgramError::BorshIoError));
+        assert_eq!(test_vault::totalAssets(&vault), Err(ProgramError::BorshIoError));
+
+        let accounts = test_vault::PreviewAccounts { vault: &vault };
+        let preview: Result<Preview, ProgramError> = accounts.invoke(&test_vault::PreviewArgs { amount: 100 });
+        assert_eq!(preview, Err(ProgramError::BorshIoError));
+        let total_assets: Result<u64, ProgramError> = test_vault::TotalAssetsAccounts { vault: &vault }
+            .invoke_with_remaining(&test_vault::TotalAssetsArgs {}, &[], &[]);
+        assert_eq!(total_assets, Err(ProgramError::BorshIoError));
+        assert!(test_vault::codec::return_data(&test_vault::ID).is_err());
+
+        println!("Return data read back from the callee");
+    }
+
+    #[test]
+    fn test_remaining_accounts() {
+        use pinocchio::instruction::AccountMeta;
+
+        println!("🔍 TESTING: Remaining accounts appended to the CPI");
+
+        let (_src_buf, src) = mock_account([1u8; 32], test_spl::ID, &[]);
+        let (_dst_buf, dst) = mock_account([2u8; 32], test_spl::ID, &[]);
+        let (_multisig_buf, multisig) = mock_account([3u8; 32], test_spl::ID, &[]);
+        let (_first_buf, first_signer) = mock_account([4u8; 32], [0u8; 32], &[]);
+        let (_second_buf, second_signer) = mock_account([5u8; 32], [0u8; 32], &[]);
+
+        let accounts = test_spl::TransferAccounts { source: &src, destination: &dst, authority: &multisig };
+        let args = test_spl::TransferArgs { amount: 100 };
+        let metas = [AccountMeta::readonly_signer(first_signer.key()), AccountMeta::readonly_signer(second_signer.key())];
+
+        // a multisig transfer: the signers follow the IDL's accounts
+        assert_eq!(accounts.invoke_with_remaining(&args, &metas, &[&first_signer, &second_signer]), Ok(()));
+        assert_eq!(accounts.invoke_with_remaining(&args, &[], &[]), Ok(()));
+
+        // every meta needs its account info, with the same key
+        assert_eq!(accounts.invoke_with_remaining(&args, &metas, &[&first_signer]), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(accounts.invoke_with_remaining(&args, &metas, &[&second_signer, &first_signer]), Err(ProgramError::InvalidArgument));
+
+        // the buffers are sized at expansion time
+        assert_eq!(test_spl::MAX_REMAINING_ACCOUNTS, 16);
+        let too_many: [AccountMeta; 17] = core::array::from_fn(|_| AccountMeta::readonly(first_signer.key()));
+        let too_many_infos = [&first_signer; 17];
+        assert_eq!(accounts.invoke_with_remaining(&args, &too_many, &too_many_infos), Err(ProgramError::InvalidArgument));
+
+        // remaining accounts are borrow-checked like the declared ones
+        let _borrow = first_signer.try_borrow_mut_data().unwrap();
+        assert_eq!(accounts.invoke_with_remaining(&args, &metas, &[&first_signer, &second_signer]), Err(ProgramError::AccountBorrowFailed));
+
+        println!("Remaining accounts passed through without allocation");
+    }
+
+    #[test]
+    fn test_signed_cpi_variants() {
+        use pinocchio::instruction::{Seed, Signer};
+        println!("🔍 TESTING: invoke_signed variants for PDA authorities");
+        
+        let (_src_buf, src) = mock_account([1u8; 32], test_spl::ID, &[]);
+        let (_dst_buf, dst) = mock_account([2u8; 32], test_spl::ID, &[]);
+        let (_vault_buf, vault) = mock_account([3u8; 32], [9u8; 32], &[]);
+        
+        let bump = [254u8];
+        let seeds = [Seed::from(b"vault"), Seed::from(&bump)];
+        let signer = Signer::from(&seeds);
+        
+        let _: fn(&AccountInfo, &AccountInfo, &AccountInfo, u64, &[Signer]) -> ProgramResult = test_spl::transfer_signed;
+        assert_eq!(test_spl::transfer_signed(&src, &dst, &vault, 100, std::slice::from_ref(&signer)), Ok(()));
+        
+        // the PDA authority is marked as a signer, its signature comes from the seeds
+        let accounts = test_spl::TransferAccounts { source: &src, destination: &dst, authority: &vault };
+        assert_eq!(meta_flags(&accounts.account_metas())[2], ([3u8; 32], false, true));
+        let args = test_spl::TransferArgs { amount: 100 };
+        assert_eq!(args.data().unwrap().as_slice(), &[3, 100, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(accounts.invoke_signed(&args, &[signer]), Ok(()));
+        
+        println!("Signed CPI variants generated");
+    }
+
     #[test]
     fn test_macro_compilation_success() {
         println!("🔍 TESTING: Generated code compiles without errors");